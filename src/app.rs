@@ -1,85 +1,859 @@
-use crate::game::{Field, GameConfig};
+use crate::game::{CellView, Coord, Field, GameConfig, GameState, MAX_BOARD_SIDE};
+use crate::replay::{Action, GameResult, Playback, Recording, Replay, Step};
+use crate::theme::Theme;
+use std::convert::TryFrom;
+use std::time::{Duration, Instant};
+use tui::layout::Rect;
 
 pub struct App<'a> {
     pub title: &'a str,
     pub should_quit: bool,
+    /// unicode symbols in the title and config, plain ASCII otherwise
     pub enhanced_graphics: bool,
-    pub click: (u16, u16),
-    pub last_reveal: usize,
+    /// glyphs and colours of the minefield
+    pub theme: Theme,
+    /// whether marking a cell cycles through a question mark after the flag
+    pub question_marks: bool,
+    /// where the cells were drawn on the last frame, used to map clicks to cells
+    pub field_area: Rect,
+    /// number of terminal columns taken by a cell
+    pub cell_width: u16,
+    /// first visible cell of the board, in the top left corner
+    pub scroll: Coord,
+    /// number of columns and rows of cells that fit on screen
+    pub view_columns: usize,
+    pub view_rows: usize,
+    /// whether the viewport scrolls to keep the cursor visible, disabled by manual scrolling
+    follow_cursor: bool,
+    /// cell targeted by the keyboard
+    pub cursor: Coord,
+    /// count typed before a command, e.g. the 5 in `5l`
+    count: usize,
+    /// time spent on the current game, stops once the game is over
+    pub elapsed: Duration,
+    /// when the first cell of the current game was revealed
+    started_at: Option<Instant>,
+    /// state of the game when the timer was last updated, to notice when it ends
+    last_state: GameState,
+    /// whether the actions are recorded, puzzles start in the middle of a game so they aren't
+    recording: bool,
+    /// actions of the current game, saved as a replay once it's over
+    steps: Vec<Step>,
+    /// when the current game was created, the time of the actions starts there
+    recording_since: Instant,
+    /// replay of the game that just ended, waiting to be saved
+    finished_replay: Option<Replay>,
+    /// replay being watched, the board is read-only meanwhile
+    pub playback: Option<Playback>,
+    /// when the last tick happened, to advance the playback
+    last_tick: Instant,
+    /// mines of a board loaded from a file, kept when restarting
+    board: Option<Vec<usize>>,
 
     pub field: Field,
 }
 
 impl<'a> App<'a> {
-    pub fn new(title: &'a str, config: &GameConfig, enhanced_graphics: bool) -> App<'a> {
+    pub fn new(
+        title: &'a str,
+        config: &GameConfig,
+        seed: Option<u64>,
+        enhanced_graphics: bool,
+        theme: Theme,
+        question_marks: bool,
+    ) -> App<'a> {
         App {
             title,
             should_quit: false,
             enhanced_graphics,
-            click: (0, 0),
-            last_reveal: 0,
-            field: Field::new(config),
+            theme,
+            question_marks,
+            field_area: Rect::default(),
+            cell_width: 1,
+            scroll: Coord { x: 0, y: 0 },
+            view_columns: 0,
+            view_rows: 0,
+            follow_cursor: true,
+            cursor: Coord { x: 0, y: 0 },
+            count: 0,
+            elapsed: Duration::from_secs(0),
+            started_at: None,
+            last_state: GameState::NotStarted,
+            recording: true,
+            steps: Vec::new(),
+            recording_since: Instant::now(),
+            finished_replay: None,
+            playback: None,
+            last_tick: Instant::now(),
+            board: None,
+            field: match seed {
+                Some(seed) => Field::with_seed(config, seed),
+                None => Field::new(config),
+            },
         }
     }
 
+    /**
+     * Play on a fixed layout of mines instead of a generated one, the config must match it
+     */
+    pub fn set_board(&mut self, mines: Vec<usize>) {
+        self.field = Field::with_mines(&self.field.config, 0, &mines);
+        self.board = Some(mines);
+    }
+
+    /**
+     * Continue a game parsed with `Field::parse`, restarting plays its mines from scratch.
+     * Replays start from hidden cells so the puzzle isn't recorded.
+     */
+    pub fn set_puzzle(&mut self, field: Field) {
+        self.board = Some(field.mine_positions());
+        self.last_state = field.state();
+        self.field = field;
+        self.recording = false;
+    }
+
+    /**
+     * Continue a saved game, recording it again if it was
+     */
+    pub fn resume(&mut self, field: Field, elapsed: Duration, recording: Option<Recording>) {
+        self.last_state = field.state();
+        self.field = field;
+        self.elapsed = elapsed;
+        self.recording = recording.is_some();
+        if let Some(recording) = recording {
+            self.steps = recording.steps;
+            self.recording_since = Instant::now()
+                .checked_sub(recording.time)
+                .unwrap_or_else(Instant::now);
+        }
+    }
+
+    /**
+     * Actions of the current game so far, to save them with it
+     */
+    pub fn recording(&self) -> Option<Recording> {
+        if self.recording {
+            Some(Recording {
+                time: self.recording_since.elapsed(),
+                steps: self.steps.clone(),
+            })
+        } else {
+            None
+        }
+    }
+
+    /**
+     * Watch a replay instead of playing
+     */
+    pub fn play(&mut self, replay: Replay) {
+        let mut playback = Playback::new(replay);
+        playback.rewind(&mut self.field, &mut self.cursor);
+        self.playback = Some(playback);
+        self.recording = false;
+        self.elapsed = Duration::from_secs(0);
+        self.last_tick = Instant::now();
+    }
+
     // TODO: refactor to handle events?
 
     pub fn on_key(&mut self, c: char) {
+        if self.playback.is_some() {
+            self.on_playback_key(c);
+            return;
+        }
+
+        if let Some(digit) = c.to_digit(10) {
+            // a leading 0 isn't a count
+            if digit > 0 || self.count > 0 {
+                self.count = self.count.saturating_mul(10).saturating_add(digit as usize);
+                return;
+            }
+        }
+
         match c {
             'q' => {
                 self.should_quit = true;
             }
             'r' => {
-                self.field = Field::new(&self.field.config);
+                self.field = match &self.board {
+                    Some(mines) => Field::with_mines(&self.field.config, 0, mines),
+                    None => Field::new(&self.field.config),
+                };
+                self.elapsed = Duration::from_secs(0);
+                self.started_at = None;
+                self.last_state = GameState::NotStarted;
+                self.recording = true;
+                self.steps.clear();
+                self.recording_since = Instant::now();
+            }
+            'k' => self.on_up(),
+            'j' => self.on_down(),
+            'h' => self.on_left(),
+            'l' => self.on_right(),
+            'K' => {
+                let count = self.take_scroll_count();
+                self.scroll_by(0, -count);
+            }
+            'J' => {
+                let count = self.take_scroll_count();
+                self.scroll_by(0, count);
+            }
+            'H' => {
+                let count = self.take_scroll_count();
+                self.scroll_by(-count, 0);
+            }
+            'L' => {
+                let count = self.take_scroll_count();
+                self.scroll_by(count, 0);
+            }
+            ' ' | '\n' => {
+                if let Some(index) = self.field.index(self.cursor) {
+                    self.reveal_or_chord(index);
+                }
+            }
+            'f' => {
+                if let Some(index) = self.field.index(self.cursor) {
+                    self.mark(index);
+                }
+            }
+            'c' => {
+                if let Some(index) = self.field.index(self.cursor) {
+                    self.record(Action::Chord(self.cursor));
+                    self.field.chord(index);
+                    self.update_timer();
+                }
+            }
+            'u' => {
+                // stops at the end of the history, the count can be huge
+                for _ in 0..self.take_count() {
+                    match self.field.undo() {
+                        Some(index) => {
+                            self.record(Action::Undo);
+                            self.cursor = self.field.coord(index);
+                        }
+                        None => break,
+                    }
+                }
+                self.update_timer();
             }
             _ => {}
         }
+        self.count = 0;
+    }
+
+    /**
+     * Controls of the playback: pause, step back and forth, change the speed or start over
+     */
+    fn on_playback_key(&mut self, c: char) {
+        let playback = match &mut self.playback {
+            Some(playback) => playback,
+            None => return,
+        };
+
+        match c {
+            'q' => self.should_quit = true,
+            ' ' => playback.paused = !playback.paused,
+            'l' => {
+                playback.paused = true;
+                playback.step_forward(&mut self.field, &mut self.cursor);
+            }
+            'h' => {
+                playback.paused = true;
+                playback.step_back(&mut self.field, &mut self.cursor);
+            }
+            'k' | '+' => playback.faster(),
+            'j' | '-' => playback.slower(),
+            'r' => playback.rewind(&mut self.field, &mut self.cursor),
+            _ => {}
+        }
+        self.elapsed = playback.clock;
+        self.follow_cursor = true;
+    }
+
+    /**
+     * How many times the next movement is repeated. Moving the cursor brings it back into view.
+     */
+    fn take_count(&mut self) -> usize {
+        let count = self.count.max(1);
+        self.count = 0;
+        self.follow_cursor = true;
+        count
+    }
+
+    /**
+     * Count of a scrolling command, scrolling further than a side of the board changes nothing
+     */
+    fn take_scroll_count(&mut self) -> isize {
+        self.take_count().min(MAX_BOARD_SIDE) as isize
+    }
+
+    /**
+     * Update the size of the visible part of the board, keeping the cursor in view if it moved
+     */
+    pub fn update_viewport(&mut self, columns: usize, rows: usize) {
+        self.view_columns = columns;
+        self.view_rows = rows;
+
+        if self.follow_cursor {
+            if self.cursor.x < self.scroll.x {
+                self.scroll.x = self.cursor.x;
+            } else if self.cursor.x >= self.scroll.x + columns {
+                self.scroll.x = self.cursor.x + 1 - columns.min(self.cursor.x + 1);
+            }
+            if self.cursor.y < self.scroll.y {
+                self.scroll.y = self.cursor.y;
+            } else if self.cursor.y >= self.scroll.y + rows {
+                self.scroll.y = self.cursor.y + 1 - rows.min(self.cursor.y + 1);
+            }
+        }
+
+        self.clamp_scroll();
+    }
+
+    /**
+     * Move the viewport without moving the cursor
+     */
+    pub fn scroll_by(&mut self, dx: isize, dy: isize) {
+        self.follow_cursor = false;
+        self.scroll.x = Self::offset(self.scroll.x, dx);
+        self.scroll.y = Self::offset(self.scroll.y, dy);
+        self.clamp_scroll();
+    }
+
+    fn offset(position: usize, delta: isize) -> usize {
+        let position = isize::try_from(position).unwrap_or(isize::MAX);
+        position.saturating_add(delta).max(0) as usize
+    }
+
+    fn clamp_scroll(&mut self) {
+        let max_x = self
+            .field
+            .config
+            .columns()
+            .saturating_sub(self.view_columns);
+        let max_y = self.field.config.rows().saturating_sub(self.view_rows);
+        self.scroll.x = self.scroll.x.min(max_x);
+        self.scroll.y = self.scroll.y.min(max_y);
+    }
+
+    /**
+     * Redo the moves undone with `u`, as many as the count
+     */
+    pub fn on_redo(&mut self) {
+        if self.playback.is_some() {
+            return;
+        }
+        // stops at the end of the history, the count can be huge
+        for _ in 0..self.take_count() {
+            match self.field.redo() {
+                Some(index) => {
+                    self.record(Action::Redo);
+                    self.cursor = self.field.coord(index);
+                }
+                None => break,
+            }
+        }
+        self.update_timer();
+    }
+
+    pub fn on_up(&mut self) {
+        if self.playback.is_some() {
+            return self.on_playback_key('k');
+        }
+        let count = self.take_count();
+        self.move_cursor(Coord {
+            x: self.cursor.x,
+            y: self.cursor.y.saturating_sub(count),
+        });
+    }
+
+    pub fn on_down(&mut self) {
+        if self.playback.is_some() {
+            return self.on_playback_key('j');
+        }
+        let count = self.take_count();
+        self.move_cursor(Coord {
+            x: self.cursor.x,
+            y: (self.cursor.y.saturating_add(count)).min(self.field.config.rows() - 1),
+        });
+    }
+
+    pub fn on_left(&mut self) {
+        if self.playback.is_some() {
+            return self.on_playback_key('h');
+        }
+        let count = self.take_count();
+        self.move_cursor(Coord {
+            x: self.cursor.x.saturating_sub(count),
+            y: self.cursor.y,
+        });
+    }
+
+    pub fn on_right(&mut self) {
+        if self.playback.is_some() {
+            return self.on_playback_key('l');
+        }
+        let count = self.take_count();
+        self.move_cursor(Coord {
+            x: (self.cursor.x.saturating_add(count)).min(self.field.config.columns() - 1),
+            y: self.cursor.y,
+        });
+    }
+
+    fn move_cursor(&mut self, cursor: Coord) {
+        if cursor != self.cursor {
+            self.cursor = cursor;
+            self.record(Action::Cursor(cursor));
+        }
     }
 
     fn to_field_index(&self, x: u16, y: u16) -> Option<usize> {
-        // positions start at 1 + remove the border on the left
-        let field_x: isize = (x as isize - 2) / 2;
-        // positions start at 1 + remove the border on the top + title
-        let field_y: isize = y as isize - 3;
-
-        if field_x >= 0
-            && field_x < self.field.config.columns as isize
-            && field_y >= 0
-            && field_y < self.field.config.rows as isize
-        {
-            Some(field_y as usize * self.field.config.columns + field_x as usize)
-        } else {
-            None
+        // mouse positions start at 1
+        let x = x.checked_sub(1)?;
+        let y = y.checked_sub(1)?;
+        let area = self.field_area;
+
+        if x < area.left() || x >= area.right() || y < area.top() || y >= area.bottom() {
+            return None;
+        }
+
+        self.field.index(Coord {
+            x: self.scroll.x + ((x - area.x) / self.cell_width) as usize,
+            y: self.scroll.y + (y - area.y) as usize,
+        })
+    }
+
+    /**
+     * Scroll with the mouse wheel over the border of the minefield: the top and bottom borders
+     * scroll horizontally, the left and right ones vertically
+     */
+    fn on_wheel(&mut self, button: termion::event::MouseButton, x: u16, y: u16) {
+        let (x, y) = match (x.checked_sub(1), y.checked_sub(1)) {
+            (Some(x), Some(y)) => (x, y),
+            _ => return,
+        };
+        let area = self.field_area;
+        let on_left_or_right = (x + 1 == area.left() || x == area.right())
+            && y + 1 >= area.top()
+            && y <= area.bottom();
+        let on_top_or_bottom = (y + 1 == area.top() || y == area.bottom())
+            && x + 1 >= area.left()
+            && x <= area.right();
+        let delta = match button {
+            termion::event::MouseButton::WheelUp => -1,
+            termion::event::MouseButton::WheelDown => 1,
+            _ => return,
+        };
+
+        if on_top_or_bottom {
+            self.scroll_by(delta, 0);
+        } else if on_left_or_right {
+            self.scroll_by(0, delta);
         }
     }
 
     pub fn on_click(&mut self, button: termion::event::MouseButton, x: u16, y: u16) {
-        self.set_click(x, y);
+        if let termion::event::MouseButton::WheelUp | termion::event::MouseButton::WheelDown =
+            button
+        {
+            self.on_wheel(button, x, y);
+            return;
+        }
+        if self.playback.is_some() {
+            return;
+        }
+
         if let Some(index) = self.to_field_index(x, y) {
-            self.last_reveal = index;
+            self.cursor = self.field.coord(index);
             match button {
                 termion::event::MouseButton::Left => {
-                    self.field.reveal_cell(self.last_reveal);
+                    self.reveal_or_chord(index);
                 }
-                termion::event::MouseButton::Middle
-                | termion::event::MouseButton::WheelDown
-                | termion::event::MouseButton::Right
-                | termion::event::MouseButton::WheelUp => {
-                    self.field.toggle_flag(self.last_reveal);
+                termion::event::MouseButton::Middle => {
+                    self.record(Action::Chord(self.cursor));
+                    self.field.chord(index);
+                }
+                termion::event::MouseButton::Right => {
+                    self.mark(index);
                 }
                 _ => {}
             }
+            self.update_timer();
+        }
+    }
+
+    /**
+     * Flag a cell, or cycle through the flag and question mark if they are enabled
+     */
+    fn mark(&mut self, index: usize) {
+        let before = self.field.view(index, false);
+        if self.question_marks {
+            self.field.cycle_mark(index);
+        } else {
+            self.field.toggle_flag(index);
+        }
+
+        let coord = self.field.coord(index);
+        match self.field.view(index, false) {
+            view if view == before => {}
+            Some(CellView::Flagged) => self.record(Action::Flag(coord)),
+            Some(CellView::Questioned) => self.record(Action::Question(coord)),
+            Some(CellView::Hidden) => self.record(Action::Unflag(coord)),
+            _ => {}
+        }
+    }
+
+    /**
+     * Reveal a hidden cell or chord on an already revealed one
+     */
+    fn reveal_or_chord(&mut self, index: usize) {
+        let coord = self.field.coord(index);
+        if self.field.is_revealed(index) {
+            self.record(Action::Chord(coord));
+            self.field.chord(index);
+        } else {
+            self.record(Action::Reveal(coord));
+            self.field.reveal_cell(index);
+        }
+        self.update_timer();
+    }
+
+    fn record(&mut self, action: Action) {
+        if self.recording {
+            self.steps.push(Step {
+                time: self.recording_since.elapsed(),
+                action,
+            });
+        }
+    }
+
+    /**
+     * Replay of the game once it's over, only given once per ending
+     */
+    pub fn take_finished_replay(&mut self) -> Option<Replay> {
+        self.finished_replay.take()
+    }
+
+    pub fn on_tick(&mut self) {
+        let delta = self.last_tick.elapsed();
+        self.last_tick = Instant::now();
+
+        match &mut self.playback {
+            Some(playback) => {
+                playback.tick(delta, &mut self.field, &mut self.cursor);
+                self.elapsed = playback.clock;
+                self.follow_cursor = true;
+            }
+            None => self.update_timer(),
         }
     }
 
+    /**
+     * Start the timer on the first reveal and stop it once the game is over. Undoing back into a
+     * game resumes the timer from where it stopped. The replay is kept when the game ends, even on
+     * its first move when the timer never started.
+     */
+    fn update_timer(&mut self) {
+        match self.field.state() {
+            GameState::Playing => {
+                let elapsed = self.elapsed;
+                let started_at = *self.started_at.get_or_insert_with(|| {
+                    Instant::now()
+                        .checked_sub(elapsed)
+                        .unwrap_or_else(Instant::now)
+                });
+                self.elapsed = started_at.elapsed();
+            }
+            GameState::NotStarted => {
+                if let Some(started_at) = self.started_at.take() {
+                    self.elapsed = started_at.elapsed();
+                }
+            }
+            GameState::Won | GameState::Lost => {
+                if let Some(started_at) = self.started_at.take() {
+                    self.elapsed = started_at.elapsed();
+                }
+                if self.recording && !self.last_state.is_over() {
+                    let mut replay = Replay::new(&self.field, self.steps.clone());
+                    replay.result = Some(GameResult::new(&self.field, self.elapsed));
+                    self.finished_replay = Some(replay);
+                }
+            }
+        }
+        self.last_state = self.field.state();
+    }
+
+    #[allow(dead_code)]
     pub fn on_press(&mut self) {
         // 🙂
         // TODO: show a 😵
         // self.field.start_press(x, y)
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::FirstClick;
+    use termion::event::MouseButton;
+
+    /// 8x30 board drawn in a 10x5 viewport, starting on the second column and third row of the
+    /// terminal
+    fn app() -> App<'static> {
+        let config = GameConfig::new(8, 30, 10, FirstClick::SafeArea).unwrap();
+        let mut app = App::new("test", &config, Some(1), true, Theme::classic(), true);
+        app.field_area = Rect::new(1, 2, 20, 5);
+        app.cell_width = 2;
+        app.update_viewport(10, 5);
+        app
+    }
+
+    fn type_keys(app: &mut App, keys: &str) {
+        for c in keys.chars() {
+            app.on_key(c);
+        }
+    }
+
+    #[test]
+    fn clicks_map_to_cells() {
+        let mut app = app();
+        // mouse positions start at 1
+        assert_eq!(app.to_field_index(2, 3), Some(0));
+        assert_eq!(app.to_field_index(3, 3), Some(0));
+        assert_eq!(app.to_field_index(4, 3), Some(1));
+        // right and bottom edges
+        assert_eq!(app.to_field_index(21, 3), Some(9));
+        assert_eq!(app.to_field_index(22, 3), None);
+        assert_eq!(app.to_field_index(2, 7), Some(4 * 30));
+        assert_eq!(app.to_field_index(2, 8), None);
+        // borders and outside of the terminal
+        assert_eq!(app.to_field_index(1, 3), None);
+        assert_eq!(app.to_field_index(2, 2), None);
+        assert_eq!(app.to_field_index(0, 0), None);
+
+        app.scroll = Coord { x: 5, y: 2 };
+        assert_eq!(app.to_field_index(2, 3), Some(2 * 30 + 5));
+        assert_eq!(app.to_field_index(21, 7), Some(6 * 30 + 14));
+    }
+
+    #[test]
+    fn viewport_follows_cursor() {
+        let mut app = app();
+        type_keys(&mut app, "12l");
+        app.update_viewport(10, 5);
+        assert_eq!(app.cursor, Coord { x: 12, y: 0 });
+        assert_eq!(app.scroll, Coord { x: 3, y: 0 });
+
+        type_keys(&mut app, "9j");
+        app.update_viewport(10, 5);
+        assert_eq!(app.cursor, Coord { x: 12, y: 7 });
+        assert_eq!(app.scroll, Coord { x: 3, y: 3 });
+
+        type_keys(&mut app, "12h");
+        app.update_viewport(10, 5);
+        assert_eq!(app.scroll, Coord { x: 0, y: 3 });
+
+        // scrolling is clamped and leaves the cursor out of view
+        type_keys(&mut app, "99L");
+        app.update_viewport(10, 5);
+        assert_eq!(app.scroll, Coord { x: 20, y: 3 });
+        assert_eq!(app.cursor, Coord { x: 0, y: 7 });
+
+        // until the cursor moves again
+        type_keys(&mut app, "l");
+        app.update_viewport(10, 5);
+        assert_eq!(app.scroll, Coord { x: 1, y: 3 });
+
+        // a bigger viewport shows the whole board
+        app.update_viewport(40, 10);
+        assert_eq!(app.scroll, Coord { x: 0, y: 0 });
+    }
+
+    #[test]
+    fn commands_act_on_the_cursor() {
+        let config = GameConfig::new(3, 3, 2, FirstClick::Unsafe).unwrap();
+        let mut app = App::new("test", &config, None, true, Theme::classic(), true);
+        app.set_board(vec![2, 8]);
+
+        // marking cycles through the flag and the question mark
+        type_keys(&mut app, "f");
+        assert_eq!(app.field.view(0, false), Some(CellView::Flagged));
+        type_keys(&mut app, "f");
+        assert_eq!(app.field.view(0, false), Some(CellView::Questioned));
+        type_keys(&mut app, "f");
+        assert_eq!(app.field.view(0, false), Some(CellView::Hidden));
+
+        type_keys(&mut app, "l ");
+        assert_eq!(app.field.view(1, false), Some(CellView::Revealed(1)));
+        assert_eq!(app.field.view(0, false), Some(CellView::Hidden));
+        type_keys(&mut app, "hj\n");
+        assert_eq!(app.field.view(3, false), Some(CellView::Revealed(0)));
+        assert_eq!(app.field.view(4, false), Some(CellView::Revealed(2)));
+        assert_eq!(app.field.view(5, false), Some(CellView::Hidden));
+
+        // chording on the 2 once both mines are flagged
+        type_keys(&mut app, "llkfjjfhkc");
+        assert_eq!(app.cursor, Coord { x: 1, y: 1 });
+        assert_eq!(app.field.view(5, false), Some(CellView::Revealed(2)));
+        assert_eq!(app.field.state(), GameState::Won);
+    }
+
+    #[test]
+    fn counts() {
+        let mut app = app();
+        type_keys(&mut app, "3l2j");
+        assert_eq!(app.cursor, Coord { x: 3, y: 2 });
+
+        // other commands drop the count
+        type_keys(&mut app, "5fl");
+        assert_eq!(app.cursor, Coord { x: 4, y: 2 });
+        type_keys(&mut app, "5xl");
+        assert_eq!(app.cursor, Coord { x: 5, y: 2 });
+
+        // a leading 0 isn't a count, but zeros after a digit are
+        type_keys(&mut app, "0l");
+        assert_eq!(app.cursor, Coord { x: 6, y: 2 });
+        type_keys(&mut app, "010l");
+        assert_eq!(app.cursor, Coord { x: 16, y: 2 });
+    }
+
+    #[test]
+    fn wheel_on_borders() {
+        let mut app = app();
+        app.scroll = Coord { x: 5, y: 1 };
+
+        // bottom and top borders scroll horizontally
+        app.on_click(MouseButton::WheelDown, 5, 8);
+        assert_eq!(app.scroll, Coord { x: 6, y: 1 });
+        app.on_click(MouseButton::WheelUp, 5, 2);
+        assert_eq!(app.scroll, Coord { x: 5, y: 1 });
+
+        // left and right borders scroll vertically
+        app.on_click(MouseButton::WheelDown, 22, 4);
+        assert_eq!(app.scroll, Coord { x: 5, y: 2 });
+        app.on_click(MouseButton::WheelUp, 1, 4);
+        assert_eq!(app.scroll, Coord { x: 5, y: 1 });
+        app.on_click(MouseButton::WheelUp, 1, 4);
+        app.on_click(MouseButton::WheelUp, 1, 4);
+        assert_eq!(app.scroll, Coord { x: 5, y: 0 });
+
+        // nothing happens inside the minefield or away from it
+        app.on_click(MouseButton::WheelDown, 5, 4);
+        app.on_click(MouseButton::WheelDown, 30, 4);
+        app.on_click(MouseButton::WheelDown, 5, 12);
+        assert_eq!(app.scroll, Coord { x: 5, y: 0 });
+        assert_eq!(app.field.state(), GameState::NotStarted);
+    }
+
+    #[test]
+    fn huge_counts_scroll_to_the_borders() {
+        let mut app = app();
+        app.scroll = Coord { x: 5, y: 1 };
+        type_keys(&mut app, "99999999999999999999L");
+        assert_eq!(app.scroll, Coord { x: 20, y: 1 });
+        type_keys(&mut app, "99999999999999999999H");
+        assert_eq!(app.scroll, Coord { x: 0, y: 1 });
+        type_keys(&mut app, "99999999999999999999J");
+        assert_eq!(app.scroll, Coord { x: 0, y: 3 });
+        type_keys(&mut app, "99999999999999999999K");
+        assert_eq!(app.scroll, Coord { x: 0, y: 0 });
+
+        app.scroll_by(isize::MAX, isize::MIN);
+        assert_eq!(app.scroll, Coord { x: 20, y: 0 });
+    }
+
+    #[test]
+    fn games_ending_on_the_first_move_are_recorded() {
+        let config = GameConfig::new(2, 2, 3, FirstClick::Unsafe).unwrap();
+        let mut app = App::new("test", &config, Some(1), true, Theme::classic(), true);
+        app.set_board(vec![0, 1, 2]);
+        app.on_key(' ');
+        assert_eq!(app.field.state(), GameState::Lost);
+        let replay = app.take_finished_replay().unwrap();
+        assert_eq!(replay.steps.len(), 1);
+        let result = replay.result.unwrap();
+        assert!(!result.won);
+        assert_eq!(result.time, Duration::from_secs(0));
+        // only once per ending
+        app.on_tick();
+        assert!(app.take_finished_replay().is_none());
+
+        let config = GameConfig::new(1, 3, 1, FirstClick::Unsafe).unwrap();
+        let mut app = App::new("test", &config, Some(1), true, Theme::classic(), true);
+        app.set_board(vec![0]);
+        type_keys(&mut app, "2l ");
+        assert_eq!(app.field.state(), GameState::Won);
+        assert!(app.take_finished_replay().unwrap().result.unwrap().won);
+
+        // and after restarting, the cursor stays where it was
+        type_keys(&mut app, "r ");
+        assert_eq!(app.field.state(), GameState::Won);
+        assert_eq!(app.take_finished_replay().unwrap().steps.len(), 1);
+    }
+
+    #[test]
+    fn resumed_games_keep_recording() {
+        let config = GameConfig::new(3, 3, 1, FirstClick::Unsafe).unwrap();
+        let mut app = App::new("test", &config, None, true, Theme::classic(), true);
+        app.set_board(vec![8]);
+        type_keys(&mut app, "f");
+        let recording = app.recording().unwrap();
+        assert_eq!(recording.steps.len(), 1);
+
+        let mut resumed = App::new("test", &config, None, true, Theme::classic(), true);
+        let field = Field::restore(app.field.save()).unwrap();
+        resumed.resume(field, app.elapsed, Some(recording));
+        type_keys(&mut resumed, "jjll ");
+        assert_eq!(resumed.field.state(), GameState::Lost);
+        let replay = resumed.take_finished_replay().unwrap();
+        assert_eq!(replay.steps.len(), 6);
+        assert_eq!(replay.steps[0].action, Action::Flag(Coord { x: 0, y: 0 }));
+        assert_eq!(replay.steps[5].action, Action::Reveal(Coord { x: 2, y: 2 }));
+
+        // unless they weren't recorded in the first place
+        let mut resumed = App::new("test", &config, None, true, Theme::classic(), true);
+        let field = Field::restore(app.field.save()).unwrap();
+        resumed.resume(field, app.elapsed, None);
+        assert!(resumed.recording().is_none());
+        type_keys(&mut resumed, "jjll ");
+        assert_eq!(resumed.field.state(), GameState::Lost);
+        assert!(resumed.take_finished_replay().is_none());
+    }
+
+    #[test]
+    fn puzzles_are_not_recorded() {
+        let puzzle = Field::parse("x.-\n11-\n---").unwrap();
+        let mut app = App::new("test", &puzzle.config, None, true, Theme::classic(), true);
+        app.set_puzzle(puzzle);
+        assert_eq!(app.field.state(), GameState::Playing);
+        type_keys(&mut app, "l ");
+        assert_eq!(app.field.state(), GameState::Won);
+        assert!(app.take_finished_replay().is_none());
+
+        // restarting plays the mines from scratch, which can be replayed
+        type_keys(&mut app, "rh ");
+        assert_eq!(app.field.state(), GameState::Lost);
+        assert!(app.take_finished_replay().is_some());
+    }
+
+    #[test]
+    fn undo_and_redo_stop_at_the_end_of_the_history() {
+        let config = GameConfig::new(3, 3, 1, FirstClick::Unsafe).unwrap();
+        let mut app = App::new("test", &config, Some(1), true, Theme::classic(), true);
+        app.set_board(vec![8]);
+        type_keys(&mut app, "flf");
+        assert_eq!(app.field.remaining_mines(), -1);
+
+        // would take forever without stopping
+        type_keys(&mut app, "99999999999999999999u");
+        assert_eq!(app.field.state(), GameState::NotStarted);
+        assert_eq!(app.field.remaining_mines(), 1);
+        assert_eq!(app.field.undo_count(), 2);
+        assert_eq!(app.cursor, Coord { x: 0, y: 0 });
 
-    pub fn set_click(&mut self, x: u16, y: u16) {
-        self.click.0 = x;
-        self.click.1 = y;
+        type_keys(&mut app, "99999999999999999999");
+        app.on_redo();
+        assert_eq!(app.field.remaining_mines(), -1);
+        assert_eq!(app.cursor, Coord { x: 1, y: 0 });
     }
 }
@@ -0,0 +1,340 @@
+use crate::game::{grid_rows, ConfigError, Field, FirstClick, GameConfig, GridError};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Biggest side of a board in the mbf format, which stores it on a byte
+const MBF_MAX_SIDE: usize = u8::MAX as usize;
+
+/**
+ * Layout of the mines of a board, to share it with other clients.
+ *
+ * Two formats are supported, picked by the extension of the file:
+ * - `.mbf`: a binary header with the width, height (one byte each) and the number of mines (two
+ *   bytes, big endian), followed by the x and y of each mine (one byte each)
+ * - anything else: a text grid with one line per row, `x` for a mine and `.` for an empty cell.
+ *   It's a puzzle where every cell is hidden, see `Field::parse`.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Board {
+    pub rows: usize,
+    pub columns: usize,
+    /// Indexes of the mines, in increasing order
+    pub mines: Vec<usize>,
+}
+
+impl Board {
+    /**
+     * Layout of a field whose mines are placed
+     */
+    pub fn from_field(field: &Field) -> Board {
+        Board {
+            rows: field.config.rows(),
+            columns: field.config.columns(),
+            mines: field.mine_positions(),
+        }
+    }
+
+    /**
+     * Config of a game on this board. Mines are already placed so the first click is unsafe.
+     */
+    pub fn config(&self) -> Result<GameConfig, BoardError> {
+        GameConfig::new(
+            self.rows,
+            self.columns,
+            self.mines.len(),
+            FirstClick::Unsafe,
+        )
+        .map_err(BoardError::Config)
+    }
+
+    pub fn read(path: &Path) -> Result<Board, BoardError> {
+        if is_mbf(path) {
+            Board::parse_mbf(&fs::read(path).map_err(BoardError::Io)?)
+        } else {
+            Board::parse_text(&fs::read_to_string(path).map_err(BoardError::Io)?)
+        }
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), BoardError> {
+        let bytes = if is_mbf(path) {
+            self.to_mbf()?
+        } else {
+            self.to_text().into_bytes()
+        };
+        fs::write(path, bytes).map_err(BoardError::Io)
+    }
+
+    /**
+     * Read a text grid, see `grid_rows` for the layout. `o` is accepted for empty cells too, like
+     * in `Field::parse`.
+     */
+    pub fn parse_text(text: &str) -> Result<Board, BoardError> {
+        let rows = grid_rows(text).map_err(BoardError::Grid)?;
+        let columns = rows[0].len();
+        let mut mines = Vec::new();
+        for (y, row) in rows.iter().enumerate() {
+            for (x, &c) in row.iter().enumerate() {
+                match c {
+                    'x' => mines.push(x + y * columns),
+                    '.' | 'o' => {}
+                    _ => {
+                        return Err(BoardError::Cell {
+                            row: y + 1,
+                            column: x + 1,
+                            found: c,
+                        })
+                    }
+                }
+            }
+        }
+
+        Ok(Board {
+            rows: rows.len(),
+            columns,
+            mines,
+        })
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::with_capacity((self.columns + 1) * self.rows);
+        let mut mines = self.mines.iter().peekable();
+        for pos in 0..self.rows * self.columns {
+            if mines.peek() == Some(&&pos) {
+                mines.next();
+                text.push('x');
+            } else {
+                text.push('.');
+            }
+            if (pos + 1) % self.columns == 0 {
+                text.push('\n');
+            }
+        }
+        text
+    }
+
+    pub fn parse_mbf(bytes: &[u8]) -> Result<Board, BoardError> {
+        if bytes.len() < 4 {
+            return Err(BoardError::Truncated);
+        }
+        let columns = bytes[0] as usize;
+        let rows = bytes[1] as usize;
+        let count = u16::from_be_bytes([bytes[2], bytes[3]]) as usize;
+        let coords = &bytes[4..];
+        if coords.len() < count * 2 {
+            return Err(BoardError::Truncated);
+        }
+
+        let mut mines = Vec::with_capacity(count);
+        for coord in coords.chunks(2).take(count) {
+            let (x, y) = (coord[0] as usize, coord[1] as usize);
+            if x >= columns || y >= rows {
+                return Err(BoardError::MineOutside { x, y });
+            }
+            mines.push(x + y * columns);
+        }
+        mines.sort_unstable();
+        if mines.windows(2).any(|pair| pair[0] == pair[1]) {
+            return Err(BoardError::DuplicateMine);
+        }
+
+        Ok(Board {
+            rows,
+            columns,
+            mines,
+        })
+    }
+
+    pub fn to_mbf(&self) -> Result<Vec<u8>, BoardError> {
+        if self.columns > MBF_MAX_SIDE
+            || self.rows > MBF_MAX_SIDE
+            || self.mines.len() > u16::MAX as usize
+        {
+            return Err(BoardError::TooLarge);
+        }
+
+        let mut bytes = Vec::with_capacity(4 + self.mines.len() * 2);
+        bytes.push(self.columns as u8);
+        bytes.push(self.rows as u8);
+        bytes.extend_from_slice(&(self.mines.len() as u16).to_be_bytes());
+        for &pos in self.mines.iter() {
+            bytes.push((pos % self.columns) as u8);
+            bytes.push((pos / self.columns) as u8);
+        }
+        Ok(bytes)
+    }
+}
+
+fn is_mbf(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .map_or(false, |extension| extension.eq_ignore_ascii_case("mbf"))
+}
+
+#[derive(Debug)]
+pub enum BoardError {
+    Io(io::Error),
+    Grid(GridError),
+    /// A character of the text grid isn't a mine nor an empty cell
+    Cell {
+        row: usize,
+        column: usize,
+        found: char,
+    },
+    /// The mbf file ends before its header or its mines
+    Truncated,
+    MineOutside {
+        x: usize,
+        y: usize,
+    },
+    DuplicateMine,
+    /// The board doesn't fit in the mbf format
+    TooLarge,
+    Config(ConfigError),
+}
+
+impl fmt::Display for BoardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BoardError::Io(err) => write!(f, "could not access the board file: {}", err),
+            BoardError::Grid(err) => write!(f, "invalid board file: {}", err),
+            BoardError::Cell { row, column, found } => write!(
+                f,
+                "unexpected '{}' on row {}, column {} of the board, expected 'x' or '.'",
+                found, row, column
+            ),
+            BoardError::Truncated => write!(f, "the board file is truncated"),
+            BoardError::MineOutside { x, y } => {
+                write!(f, "the mine at {},{} is outside of the board", x, y)
+            }
+            BoardError::DuplicateMine => write!(f, "the board has the same mine twice"),
+            BoardError::TooLarge => write!(
+                f,
+                "mbf files can't hold boards bigger than {}x{}",
+                MBF_MAX_SIDE, MBF_MAX_SIDE
+            ),
+            BoardError::Config(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for BoardError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::GameState;
+
+    #[test]
+    fn text_boards() {
+        let board = Board::parse_text("\n.x..\nx...\n...x\n\n").unwrap();
+        assert_eq!(
+            board,
+            Board {
+                rows: 3,
+                columns: 4,
+                mines: vec![1, 4, 11],
+            }
+        );
+        assert_eq!(board.to_text(), ".x..\nx...\n...x\n");
+        assert_eq!(Board::parse_text(&board.to_text()).unwrap(), board);
+
+        // the grids used by the tests
+        assert_eq!(Board::parse_text("xo\nox").unwrap().mines, vec![0, 3]);
+
+        assert!(matches!(
+            Board::parse_text("\n\n"),
+            Err(BoardError::Grid(GridError::Empty))
+        ));
+        assert!(matches!(
+            Board::parse_text("x..\nx."),
+            Err(BoardError::Grid(GridError::RowLength {
+                row: 2,
+                expected: 3,
+                found: 2
+            }))
+        ));
+        // a revealed mine only makes sense in a puzzle
+        assert!(matches!(
+            Board::parse_text("X.."),
+            Err(BoardError::Cell { found: 'X', .. })
+        ));
+        assert!(matches!(
+            Board::parse_text("x..\n.?."),
+            Err(BoardError::Cell {
+                row: 2,
+                column: 2,
+                found: '?'
+            })
+        ));
+    }
+
+    #[test]
+    fn mbf_boards() {
+        let board = Board {
+            rows: 2,
+            columns: 3,
+            mines: vec![2, 3],
+        };
+        let bytes = board.to_mbf().unwrap();
+        assert_eq!(bytes, vec![3, 2, 0, 2, 2, 0, 0, 1]);
+        assert_eq!(Board::parse_mbf(&bytes).unwrap(), board);
+
+        // mines in any order
+        assert_eq!(Board::parse_mbf(&[3, 2, 0, 2, 0, 1, 2, 0]).unwrap(), board);
+        assert!(matches!(
+            Board::parse_mbf(&[3, 2, 0]),
+            Err(BoardError::Truncated)
+        ));
+        assert!(matches!(
+            Board::parse_mbf(&[3, 2, 0, 2, 2, 0]),
+            Err(BoardError::Truncated)
+        ));
+        assert!(matches!(
+            Board::parse_mbf(&[3, 2, 0, 1, 3, 0]),
+            Err(BoardError::MineOutside { x: 3, y: 0 })
+        ));
+        assert!(matches!(
+            Board::parse_mbf(&[3, 2, 0, 2, 1, 1, 1, 1]),
+            Err(BoardError::DuplicateMine)
+        ));
+        assert!(matches!(
+            Board {
+                rows: 1,
+                columns: 300,
+                mines: vec![0],
+            }
+            .to_mbf(),
+            Err(BoardError::TooLarge)
+        ));
+    }
+
+    #[test]
+    fn boards_and_fields() {
+        let board = Board::parse_text("x..\n...\n..x").unwrap();
+        let config = board.config().unwrap();
+        assert_eq!(config.mines(), 2);
+
+        let field = Field::with_mines(&config, 0, &board.mines);
+        assert_eq!(Board::from_field(&field), board);
+        assert_eq!(
+            field.as_text_ascii(true),
+            "\
+x1-
+121
+-1x\
+"
+        );
+
+        // exported boards can be played as puzzles
+        let puzzle = Field::parse(&board.to_text()).unwrap();
+        assert_eq!(puzzle.state(), GameState::NotStarted);
+        assert_eq!(Board::from_field(&puzzle), board);
+
+        assert!(matches!(
+            Board::parse_text("xx\nxx").unwrap().config(),
+            Err(BoardError::Config(ConfigError::TooManyMines { .. }))
+        ));
+    }
+}
@@ -1,20 +1,122 @@
 use std::fmt;
-use std::fmt::format;
 
 extern crate rand;
 use rand::thread_rng;
 use rand::Rng;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use std::convert::TryFrom;
+
+/// Which cells are kept free of mines on the first reveal
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum FirstClick {
+    /// Mines are placed when the field is created, the first click can hit one
+    Unsafe,
+    /// Mines are placed on the first reveal, never on the clicked cell
+    SafeCell,
+    /// Mines are placed on the first reveal, never on the clicked cell or its neighbours
+    SafeArea,
+}
+
+impl std::str::FromStr for FirstClick {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "unsafe" => Ok(FirstClick::Unsafe),
+            "cell" => Ok(FirstClick::SafeCell),
+            "area" => Ok(FirstClick::SafeArea),
+            _ => Err(format!(
+                "unknown first click mode '{}', expected one of: unsafe, cell, area",
+                s
+            )),
+        }
+    }
+}
 
-use std::collections::HashSet;
-use std::iter::FromIterator;
+impl fmt::Display for FirstClick {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            FirstClick::Unsafe => "unsafe",
+            FirstClick::SafeCell => "cell",
+            FirstClick::SafeArea => "area",
+        })
+    }
+}
 
-#[derive(Clone)]
+/// Size and number of mines of a board. Can only be built through `GameConfig::new` or the
+/// presets, so a `Field` never gets a board it cannot fill. Deserializing goes through
+/// `GameConfig::new` too.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "RawGameConfig")]
 pub struct GameConfig {
-    pub rows: usize,
-    pub columns: usize,
-    pub mines: usize,
+    rows: usize,
+    columns: usize,
+    mines: usize,
+    first_click: FirstClick,
+    /// flagged cells are left alone by reveals and cascades
+    protect_flags: bool,
+}
+
+/// A `GameConfig` as read from a file, before it is validated
+#[derive(Deserialize)]
+struct RawGameConfig {
+    rows: usize,
+    columns: usize,
+    mines: usize,
+    first_click: FirstClick,
+    protect_flags: bool,
+}
+
+impl TryFrom<RawGameConfig> for GameConfig {
+    type Error = ConfigError;
+
+    fn try_from(raw: RawGameConfig) -> Result<GameConfig, ConfigError> {
+        GameConfig::new(raw.rows, raw.columns, raw.mines, raw.first_click)
+            .map(|config| config.with_flag_protection(raw.protect_flags))
+    }
+}
+
+/// Biggest number of rows or columns a board can have
+pub const MAX_BOARD_SIDE: usize = 1000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigError {
+    /// The board has no rows or no columns
+    ZeroDimensions { rows: usize, columns: usize },
+    /// There must be at least one cell without a mine
+    TooManyMines { mines: usize, cells: usize },
+    /// One of the sides is bigger than `MAX_BOARD_SIDE`
+    BoardTooLarge { rows: usize, columns: usize },
 }
 
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::ZeroDimensions { rows, columns } => write!(
+                f,
+                "the board must have at least one row and one column, got {}x{}",
+                columns, rows
+            ),
+            ConfigError::TooManyMines { mines, cells } => write!(
+                f,
+                "too many mines: the board has {} cells, there must be fewer than {} mines (got {})",
+                cells, cells, mines
+            ),
+            ConfigError::BoardTooLarge { rows, columns } => write!(
+                f,
+                "the board is too large: {}x{}, each side must be at most {}",
+                columns, rows, MAX_BOARD_SIDE
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
 impl fmt::Display for GameConfig {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
@@ -25,35 +127,250 @@ impl fmt::Display for GameConfig {
     }
 }
 
-impl GameConfig {}
+impl GameConfig {
+    pub fn new(
+        rows: usize,
+        columns: usize,
+        mines: usize,
+        first_click: FirstClick,
+    ) -> Result<GameConfig, ConfigError> {
+        if rows == 0 || columns == 0 {
+            return Err(ConfigError::ZeroDimensions { rows, columns });
+        }
+
+        if rows > MAX_BOARD_SIDE || columns > MAX_BOARD_SIDE {
+            return Err(ConfigError::BoardTooLarge { rows, columns });
+        }
+
+        let cells = rows * columns;
+        if mines >= cells {
+            return Err(ConfigError::TooManyMines { mines, cells });
+        }
+
+        Ok(GameConfig {
+            rows,
+            columns,
+            mines,
+            first_click,
+            protect_flags: true,
+        })
+    }
+
+    /**
+     * Whether flagged cells are immune to reveals and cascades. Enabled by default, disabling it
+     * lets a click on a flag reveal it and cascades clear the flags they run into.
+     */
+    pub fn with_flag_protection(mut self, protect_flags: bool) -> GameConfig {
+        self.protect_flags = protect_flags;
+        self
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    pub fn mines(&self) -> usize {
+        self.mines
+    }
+
+    pub fn first_click(&self) -> FirstClick {
+        self.first_click
+    }
+
+    pub fn protect_flags(&self) -> bool {
+        self.protect_flags
+    }
+}
 
 pub const CONFIG_BEGINNER: GameConfig = GameConfig {
     rows: 9,
     columns: 9,
     mines: 10,
+    first_click: FirstClick::SafeArea,
+    protect_flags: true,
 };
 
 pub const CONFIG_INTERMEDIATE: GameConfig = GameConfig {
     rows: 16,
     columns: 16,
     mines: 40,
+    first_click: FirstClick::SafeArea,
+    protect_flags: true,
 };
 
 pub const CONFIG_EXPERT: GameConfig = GameConfig {
     rows: 16,
     columns: 30,
     mines: 99,
+    first_click: FirstClick::SafeArea,
+    protect_flags: true,
 };
 
+/// Standard board presets
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Difficulty {
+    Beginner,
+    Intermediate,
+    Expert,
+}
+
+impl Difficulty {
+    pub fn config(self) -> GameConfig {
+        match self {
+            Difficulty::Beginner => CONFIG_BEGINNER,
+            Difficulty::Intermediate => CONFIG_INTERMEDIATE,
+            Difficulty::Expert => CONFIG_EXPERT,
+        }
+    }
+}
+
+impl std::str::FromStr for Difficulty {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "beginner" => Ok(Difficulty::Beginner),
+            "intermediate" => Ok(Difficulty::Intermediate),
+            "expert" => Ok(Difficulty::Expert),
+            _ => Err(format!(
+                "unknown difficulty '{}', expected one of: beginner, intermediate, expert",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum GameState {
+    /// No cell has been revealed yet
+    NotStarted,
+    Playing,
+    /// Every cell that isn't a mine has been revealed
+    Won,
+    /// A mine has been revealed
+    Lost,
+}
+
+impl GameState {
+    pub fn is_over(self) -> bool {
+        matches!(self, GameState::Won | GameState::Lost)
+    }
+}
+
+/// Position of a cell on the board, `x` being the column and `y` the row
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Coord {
+    pub x: usize,
+    pub y: usize,
+}
+
+/// Offsets of the 8 cells around a cell, row by row
+const NEIGHBOUR_OFFSETS: [(isize, isize); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+/// Iterator over the indexes of the cells around a cell, see `Field::neighbours`
+pub struct Neighbours {
+    center: Coord,
+    columns: usize,
+    rows: usize,
+    offset: usize,
+}
+
+impl Iterator for Neighbours {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        while let Some(&(dx, dy)) = NEIGHBOUR_OFFSETS.get(self.offset) {
+            self.offset += 1;
+            let x = self.center.x as isize + dx;
+            let y = self.center.y as isize + dy;
+            if x >= 0 && y >= 0 && (x as usize) < self.columns && (y as usize) < self.rows {
+                return Some(x as usize + y as usize * self.columns);
+            }
+        }
+
+        None
+    }
+}
+
+/// What a move on the field did
+#[derive(Debug, Default, PartialEq)]
+pub struct RevealOutcome {
+    /// Cells revealed by the move, in the order they were opened
+    pub opened: Vec<usize>,
+    /// Whether one of the opened cells is a mine
+    pub hit_mine: bool,
+}
+
+impl RevealOutcome {
+    fn append(&mut self, other: RevealOutcome) {
+        self.opened.extend(other.opened);
+        self.hit_mine |= other.hit_mine;
+    }
+}
+
+/// A cell changed by a move
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct CellChange {
+    pos: usize,
+    before: FieldCellState,
+    after: FieldCellState,
+}
+
+/// A move of the player and every cell it changed, so it can be undone and redone
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Move {
+    /// Cell the move was played on
+    pub pos: usize,
+    /// State of the game before and after the move
+    states: (GameState, GameState),
+    /// Cells kept free of mines when the move placed them, on the first reveal
+    placed_mines: Option<HashSet<usize>>,
+    // tables come last in TOML
+    changes: Vec<CellChange>,
+}
+
+/// How a cell is shown to the player
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CellView {
+    Hidden,
+    Flagged,
+    /// Marked as uncertain by the player
+    Questioned,
+    /// Number of mines around the cell
+    Revealed(u8),
+    /// A mine that wasn't hit
+    Mine,
+    /// A mine revealed by the player
+    ExplodedMine,
+    /// A flag on a cell without a mine
+    WrongFlag,
+}
+
 pub enum FieldCellType {
     Mine,
-    Empty(u32), // TODO: can we just use u8?
+    Empty(u8),
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum FieldCellState {
     Hidden,
     Revealed,
     Flagged,
+    /// Marked as uncertain, behaves like a hidden cell
+    Questioned,
 }
 
 pub struct FieldCell {
@@ -62,49 +379,102 @@ pub struct FieldCell {
 }
 
 impl FieldCell {
+    /**
+     * Cell written with `marker`, as read by `Field::parse`. Numbers are computed afterwards, the
+     * digit of a revealed cell is left for the caller to check.
+     */
+    fn from_marker(c: char) -> Option<FieldCell> {
+        let (state, mine) = match c {
+            '.' | 'o' => (FieldCellState::Hidden, false),
+            'x' => (FieldCellState::Hidden, true),
+            '-' | '0'..='8' => (FieldCellState::Revealed, false),
+            'X' => (FieldCellState::Revealed, true),
+            'f' => (FieldCellState::Flagged, false),
+            'F' => (FieldCellState::Flagged, true),
+            'q' => (FieldCellState::Questioned, false),
+            'Q' => (FieldCellState::Questioned, true),
+            _ => return None,
+        };
+        Some(FieldCell {
+            state,
+            cell_type: if mine {
+                FieldCellType::Mine
+            } else {
+                FieldCellType::Empty(0)
+            },
+        })
+    }
+
+    /**
+     * Marker of the cell in the grids of `Field::parse`, uppercase or `x` for mines
+     */
+    fn marker(&self) -> char {
+        match (&self.state, &self.cell_type) {
+            (FieldCellState::Hidden, FieldCellType::Mine) => 'x',
+            (FieldCellState::Hidden, FieldCellType::Empty(_)) => '.',
+            (FieldCellState::Revealed, FieldCellType::Mine) => 'X',
+            (FieldCellState::Revealed, FieldCellType::Empty(0)) => '-',
+            (FieldCellState::Revealed, FieldCellType::Empty(n)) => (b'0' + *n) as char,
+            (FieldCellState::Flagged, FieldCellType::Mine) => 'F',
+            (FieldCellState::Flagged, FieldCellType::Empty(_)) => 'f',
+            (FieldCellState::Questioned, FieldCellType::Mine) => 'Q',
+            (FieldCellState::Questioned, FieldCellType::Empty(_)) => 'q',
+        }
+    }
+
     pub fn as_unicode_str(&self, force_reveal: bool) -> String {
         if force_reveal {
             self.as_revealed_str()
         } else {
             match self.state {
                 FieldCellState::Hidden => String::from("🔲"),
+                FieldCellState::Questioned => String::from("❓"),
                 _ => self.as_revealed_str(),
             }
         }
     }
 
-    pub fn as_number(&self, force_reveal: bool) -> u32 {
-        if force_reveal {
-            self.as_revealed_number()
-        } else {
-            match self.state {
-                FieldCellState::Hidden => 10,
-                _ => self.as_revealed_number(),
+    /**
+     * What the player gets to see of the cell. `reveal_all` shows what is under hidden cells and
+     * whether flags were right, like at the end of a game.
+     */
+    pub fn view(&self, reveal_all: bool) -> CellView {
+        match (&self.state, &self.cell_type) {
+            (FieldCellState::Revealed, FieldCellType::Mine) => CellView::ExplodedMine,
+            (FieldCellState::Revealed, FieldCellType::Empty(n)) => CellView::Revealed(*n),
+            (FieldCellState::Flagged, FieldCellType::Empty(_)) if reveal_all => CellView::WrongFlag,
+            (FieldCellState::Flagged, _) => CellView::Flagged,
+            (FieldCellState::Hidden, FieldCellType::Mine)
+            | (FieldCellState::Questioned, FieldCellType::Mine)
+                if reveal_all =>
+            {
+                CellView::Mine
             }
+            (FieldCellState::Hidden, FieldCellType::Empty(n))
+            | (FieldCellState::Questioned, FieldCellType::Empty(n))
+                if reveal_all =>
+            {
+                CellView::Revealed(*n)
+            }
+            (FieldCellState::Hidden, _) => CellView::Hidden,
+            (FieldCellState::Questioned, _) => CellView::Questioned,
         }
     }
 
+    #[cfg(test)]
     fn as_ascii_str(&self, force_reveal: bool) -> String {
         if force_reveal {
             self.as_revealed_ascii_str()
         } else {
             match self.state {
                 FieldCellState::Hidden => String::from("?"),
+                FieldCellState::Questioned => String::from("q"),
                 _ => self.as_revealed_ascii_str(),
             }
         }
     }
 
-    pub fn as_revealed_number(&self) -> u32 {
-        match self.state {
-            FieldCellState::Flagged => 8,
-            _ => match self.cell_type {
-                FieldCellType::Mine => 9,
-                FieldCellType::Empty(mines) => mines,
-            },
-        }
-    }
-
+    #[cfg(test)]
     pub fn as_revealed_ascii_str(&self) -> String {
         match self.state {
             FieldCellState::Flagged => String::from("f"),
@@ -135,292 +505,954 @@ impl FieldCell {
     }
 }
 
+/// Everything needed to continue a game later, see `Field::save` and `Field::restore`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavedField {
+    seed: u64,
+    state: GameState,
+    undo_count: usize,
+    mines_placed: bool,
+    /// One line per row, with the markers of `Field::parse` so the mines come along
+    cells: Vec<String>,
+    config: GameConfig,
+    // empty arrays are written as values, which TOML doesn't allow after the config table
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    history: Vec<Move>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    undone: Vec<Move>,
+}
+
+/// Why a `SavedField` cannot be restored
+#[derive(Debug, Clone, PartialEq)]
+pub enum RestoreError {
+    /// The cells don't match the size of the board, use an unknown marker or a wrong number
+    Cells,
+    /// The mines don't match the config
+    Mines,
+    /// A move changes a cell outside of the board, or keeps too many cells free of mines
+    History,
+}
+
+impl fmt::Display for RestoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RestoreError::Cells => write!(f, "the cells don't match the board"),
+            RestoreError::Mines => write!(f, "the mines don't match the config"),
+            RestoreError::History => write!(
+                f,
+                "the moves don't fit on the board or leave no room for the mines"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RestoreError {}
+
+/// Why a text grid cannot be read, see `grid_rows`
+#[derive(Debug, Clone, PartialEq)]
+pub enum GridError {
+    /// There are no rows
+    Empty,
+    RowLength {
+        row: usize,
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl fmt::Display for GridError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GridError::Empty => write!(f, "the grid has no rows"),
+            GridError::RowLength {
+                row,
+                expected,
+                found,
+            } => write!(
+                f,
+                "row {} of the grid has {} cells, expected {}",
+                row, found, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GridError {}
+
+/**
+ * Cells of a text grid, one row per line. Blank lines around the grid are ignored and every row
+ * must have as many cells as the first one. Used by `Field::parse` and the board files, which
+ * share their markers.
+ */
+pub fn grid_rows(text: &str) -> Result<Vec<Vec<char>>, GridError> {
+    let lines: Vec<&str> = text
+        .lines()
+        .map(str::trim_end)
+        .skip_while(|line| line.is_empty())
+        .collect();
+    let lines = match lines.iter().rposition(|line| !line.is_empty()) {
+        Some(last) => &lines[..=last],
+        None => return Err(GridError::Empty),
+    };
+
+    let columns = lines[0].chars().count();
+    let mut rows = Vec::with_capacity(lines.len());
+    for (y, line) in lines.iter().enumerate() {
+        let row: Vec<char> = line.chars().collect();
+        if row.len() != columns {
+            return Err(GridError::RowLength {
+                row: y + 1,
+                expected: columns,
+                found: row.len(),
+            });
+        }
+        rows.push(row);
+    }
+
+    Ok(rows)
+}
+
+/// Why `Field::parse` cannot read a board
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    Grid(GridError),
+    /// A character isn't one of the cell markers
+    Cell {
+        row: usize,
+        column: usize,
+        found: char,
+    },
+    /// A revealed number doesn't match the mines around it
+    Number {
+        row: usize,
+        column: usize,
+        expected: u8,
+        found: u8,
+    },
+    Config(ConfigError),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Grid(err) => err.fmt(f),
+            ParseError::Cell { row, column, found } => write!(
+                f,
+                "unexpected '{}' on row {}, column {} of the board",
+                found, row, column
+            ),
+            ParseError::Number {
+                row,
+                column,
+                expected,
+                found,
+            } => write!(
+                f,
+                "the cell on row {}, column {} has {} mines around, not {}",
+                row, column, expected, found
+            ),
+            ParseError::Config(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
 pub struct Field {
     pub config: GameConfig,
     cells: Vec<FieldCell>,
     mines: HashSet<usize>,
+    /// seed of the rng used to place the mines, 0 for fields that weren't generated
+    seed: u64,
+    /// false until the mines are placed, which can be deferred to the first reveal
+    mines_placed: bool,
+    state: GameState,
+    /// moves played so far, the last one is undone first
+    history: Vec<Move>,
+    /// undone moves, the last one is redone first. Cleared by a new move
+    undone: Vec<Move>,
+    /// cells changed by the move being played
+    changes: Vec<CellChange>,
+    /// how many moves were undone, a game with undos isn't a clean game
+    undo_count: usize,
+    /// number of flagged cells, kept up to date for the mines counter
+    flags: usize,
+    /// 3BV of the board, computed when the mines are placed
+    bbbv: Option<usize>,
 }
 
 impl Field {
     pub fn new(config: &GameConfig) -> Field {
-        let mut rng = thread_rng();
-        let size = (config.rows * config.columns) as usize;
+        Field::with_seed(config, thread_rng().gen())
+    }
+
+    /**
+     * Create a field whose mines are placed by an rng seeded with `seed`. The same seed, config
+     * and first click always give the same board.
+     */
+    pub fn with_seed(config: &GameConfig, seed: u64) -> Field {
+        let size = config.rows * config.columns;
         let mut field = Field {
             config: config.clone(),
             cells: Vec::with_capacity(size),
             mines: HashSet::new(),
+            seed,
+            mines_placed: false,
+            state: GameState::NotStarted,
+            history: Vec::new(),
+            undone: Vec::new(),
+            changes: Vec::new(),
+            undo_count: 0,
+            flags: 0,
+            bbbv: None,
         };
 
-        while field.mines.len() < config.mines {
-            field.mines.insert(rng.gen_range(0, size));
-        }
-
-        for i in 0..size {
+        for _ in 0..size {
             field.cells.push(FieldCell {
                 state: FieldCellState::Hidden,
-                cell_type: if field.mines.contains(&i) {
-                    FieldCellType::Mine
-                } else {
-                    FieldCellType::Empty(0)
-                },
+                cell_type: FieldCellType::Empty(0),
             });
         }
 
-        field.compute_field();
+        if config.first_click == FirstClick::Unsafe {
+            field.place_mines(&HashSet::new());
+        }
 
         field
     }
 
-    fn increment_cell_count(&mut self, i: usize) {
-        if let Some(cell) = self.cells.get_mut(i) {
-            if let FieldCellType::Empty(n) = &mut cell.cell_type {
-                *n += 1;
-            }
+    /**
+     * Create a field with the mines at the given indexes, which must be inside of the board, e.g.
+     * to play a recorded game again
+     */
+    pub fn with_mines(config: &GameConfig, seed: u64, mines: &[usize]) -> Field {
+        let mut field = Field::with_seed(config, seed);
+        for cell in field.cells.iter_mut() {
+            cell.cell_type = FieldCellType::Empty(0);
+        }
+        field.mines = mines.iter().copied().collect();
+        for &pos in mines {
+            field.cells[pos].cell_type = FieldCellType::Mine;
         }
-        // println!("{}", self.as_text_ascii(true));
-        // println!("===");
+        field.mines_placed = true;
+        field.compute_field();
+
+        field
     }
 
     /**
-     * Compute the field (number of mines) based on the current config. Should only be called once
+     * Position of the cell at `pos` on the board
      */
-    fn compute_field(&mut self) {
-        // increment counters
-        for i in 0..self.cells.len() {
-            if self.mines.contains(&i) {
-                let has_left = i % self.config.columns > 0;
-                let has_right = i % self.config.columns < self.config.columns - 1;
-                // up
-                if i > self.config.columns {
-                    let up_pos = i - self.config.columns;
-                    self.increment_cell_count(up_pos);
-
-                    // up left
-                    if has_left {
-                        self.increment_cell_count(up_pos - 1);
-                    }
-
-                    // up right
-                    if has_right {
-                        self.increment_cell_count(up_pos + 1);
-                    }
-                }
-
-                // down
-                if i / self.config.columns < self.config.rows - 1 {
-                    let down_pos = i + self.config.columns;
-                    self.increment_cell_count(down_pos);
-
-                    // down left
-                    if has_left {
-                        self.increment_cell_count(down_pos - 1);
-                    }
-
-                    // down right
-                    if has_right {
-                        self.increment_cell_count(down_pos + 1);
-                    }
-                }
-
-                // left
-                if has_left {
-                    self.increment_cell_count(i - 1);
-                }
+    pub fn coord(&self, pos: usize) -> Coord {
+        Coord {
+            x: pos % self.config.columns,
+            y: pos / self.config.columns,
+        }
+    }
 
-                // right
-                if has_right {
-                    self.increment_cell_count(i + 1);
-                }
-            }
+    /**
+     * Index of the cell at `coord`, if it's inside of the board
+     */
+    pub fn index(&self, coord: Coord) -> Option<usize> {
+        if coord.x < self.config.columns && coord.y < self.config.rows {
+            Some(coord.x + coord.y * self.config.columns)
+        } else {
+            None
         }
     }
 
-    pub fn toggle_flag(&mut self, pos: usize) {
-        if let Some(cell) = self.cells.get_mut(pos) {
-            cell.state = match cell.state {
-                FieldCellState::Flagged => FieldCellState::Hidden,
-                _ => FieldCellState::Flagged,
-            }
+    /**
+     * Iterate over the indexes of the (up to 8) cells around `pos`
+     */
+    pub fn neighbours(&self, pos: usize) -> Neighbours {
+        Neighbours {
+            center: self.coord(pos),
+            columns: self.config.columns,
+            rows: self.config.rows,
+            offset: 0,
         }
     }
 
-    pub fn reveal_cell(&mut self, pos: usize) -> bool {
-        if let Some(cell) = self.cells.get_mut(pos) {
-            match cell.state {
-                // a flagged cell cannot be revealed when clicked on
-                // FieldCellState::Flagged => return false,
-                FieldCellState::Revealed => return false,
-                _ => {
-                    cell.state = FieldCellState::Revealed;
-                    match cell.cell_type {
-                        FieldCellType::Mine => return true,
-                        FieldCellType::Empty(n) => {
-                            if n == 0 {
-                                // reveal others
-                                let has_left = pos % self.config.columns > 0;
-                                let has_right = pos % self.config.columns < self.config.columns - 1;
-                                if pos > self.config.columns {
-                                    let up_pos = pos - self.config.columns;
-                                    self.reveal_cell(up_pos);
-
-                                    // up left
-                                    if has_left {
-                                        self.reveal_cell(up_pos - 1);
-                                    }
-
-                                    // up right
-                                    if has_right {
-                                        self.reveal_cell(up_pos + 1);
-                                    }
-                                }
-
-                                // down
-                                if pos / self.config.columns < self.config.rows - 1 {
-                                    let down_pos = pos + self.config.columns;
-                                    self.reveal_cell(down_pos);
-
-                                    // down left
-                                    if has_left {
-                                        self.reveal_cell(down_pos - 1);
-                                    }
-
-                                    // down right
-                                    if has_right {
-                                        self.reveal_cell(down_pos + 1);
-                                    }
-                                }
-
-                                // left
-                                if has_left {
-                                    self.reveal_cell(pos - 1);
-                                }
-
-                                // right
-                                if has_right {
-                                    self.reveal_cell(pos + 1);
-                                }
-                            }
-                        }
-                    }
+    /**
+     * Cells that must stay free of mines when the first reveal happens at `pos`
+     */
+    fn first_click_safe_cells(&self, pos: usize) -> HashSet<usize> {
+        let mut safe = HashSet::new();
+        match self.config.first_click {
+            FirstClick::Unsafe => {}
+            FirstClick::SafeCell => {
+                safe.insert(pos);
+            }
+            FirstClick::SafeArea => {
+                safe.insert(pos);
+                safe.extend(self.neighbours(pos));
+                // not enough room left for the mines, only keep the clicked cell safe
+                if self.cells.len() - safe.len() < self.config.mines {
+                    safe.clear();
+                    safe.insert(pos);
                 }
             }
         }
 
-        false
+        safe
     }
 
     /**
-     * Create a field mine from a vec of strings. Used for tests.
+     * Randomly place the mines on cells outside of `safe` and compute the numbers. The generator
+     * is named rather than `StdRng` so a seed gives the same board across versions of rand.
      */
-    pub fn from(field_text: Vec<&str>) -> Field {
-        let config = GameConfig {
-            rows: field_text.len(),
-            columns: field_text.get(0).unwrap().len(),
-            mines: field_text.iter().map(|c| c.matches("x").count()).sum(),
-        };
-        let size = config.rows * config.columns;
-
-        let mut field = Field {
-            config,
-            cells: Vec::with_capacity(size),
-            mines: HashSet::new(),
-        };
-
-        for (y, line) in field_text.iter().enumerate() {
-            for (x, cell) in line.chars().enumerate() {
-                if cell == 'x' {
-                    field.mines.insert(x + y * field.config.columns);
-                }
-                field.cells.push(FieldCell {
-                    state: FieldCellState::Hidden,
-                    cell_type: if cell == 'x' {
-                        FieldCellType::Mine
-                    } else {
-                        FieldCellType::Empty(0)
-                    },
-                });
+    fn place_mines(&mut self, safe: &HashSet<usize>) {
+        let mut rng = ChaCha8Rng::seed_from_u64(self.seed);
+        let size = self.cells.len();
+
+        while self.mines.len() < self.config.mines {
+            let pos = rng.gen_range(0, size);
+            if !safe.contains(&pos) {
+                self.mines.insert(pos);
             }
         }
 
-        field.compute_field();
+        for &pos in self.mines.iter() {
+            self.cells[pos].cell_type = FieldCellType::Mine;
+        }
+        self.mines_placed = true;
 
-        field
+        self.compute_field();
     }
 
-    pub fn format(&self, show_all: bool) -> String {
-        let mut text = String::from("\n");
-        let mut i = 0usize;
-        let len = self.cells.len();
-        while i < len {
-            if i > 0 && i % self.config.columns == 0 {
-                // left padding
-                text.push_str("\n");
-            }
-            if i % self.config.columns == 0 {
-                // left padding
-                text.push_str("  ");
+    /**
+     * Compute the field (number of mines) based on the current config, and its 3BV once the mines
+     * are placed. Should only be called once
+     */
+    fn compute_field(&mut self) {
+        let mines: Vec<usize> = self.mines.iter().copied().collect();
+        for mine in mines {
+            for i in self.neighbours(mine) {
+                if let FieldCellType::Empty(n) = &mut self.cells[i].cell_type {
+                    *n += 1;
+                }
             }
+        }
+        self.bbbv = if self.mines_placed {
+            Some(self.compute_bbbv())
+        } else {
+            None
+        };
+    }
 
-            let cell = self.cells.get(i).unwrap();
+    /**
+     * Number of mines minus the number of flags, can be negative when too many flags were placed
+     */
+    pub fn remaining_mines(&self) -> isize {
+        self.config.mines as isize - self.flags as isize
+    }
 
-            text.push_str(&cell.as_unicode_str(show_all));
+    /**
+     * Number of flagged cells, for the fields built from their cells
+     */
+    fn count_flags(&self) -> usize {
+        self.cells
+            .iter()
+            .filter(|cell| cell.state == FieldCellState::Flagged)
+            .count()
+    }
 
-            i += 1;
+    /**
+     * Change the state of a cell, keeping count of the flags
+     */
+    fn put_state(&mut self, pos: usize, state: FieldCellState) {
+        let cell = &mut self.cells[pos];
+        if cell.state == FieldCellState::Flagged {
+            self.flags -= 1;
         }
-        text.push('\n');
-        text
+        if state == FieldCellState::Flagged {
+            self.flags += 1;
+        }
+        cell.state = state;
     }
 
-    pub fn as_text_ascii(&self, show_all: bool) -> String {
-        let mut i = 0usize;
-        let len = self.cells.len();
-        let mut text = String::new();
-        while i < len {
-            if i > 0 && i % self.config.columns == 0 {
-                // new line
-                text.push('\n');
-            }
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
 
-            let cell = self.cells.get(i).unwrap();
+    pub fn state(&self) -> GameState {
+        self.state
+    }
 
-            text.push_str(&cell.as_ascii_str(show_all));
+    /**
+     * Whether every cell that isn't a mine has been revealed
+     */
+    fn is_cleared(&self) -> bool {
+        self.cells.iter().all(|cell| match cell.cell_type {
+            FieldCellType::Mine => true,
+            FieldCellType::Empty(_) => matches!(cell.state, FieldCellState::Revealed),
+        })
+    }
 
-            i += 1;
+    pub fn toggle_flag(&mut self, pos: usize) {
+        if self.state.is_over() || pos >= self.cells.len() {
+            return;
         }
 
-        text
+        let state = match self.cells[pos].state {
+            FieldCellState::Revealed => FieldCellState::Revealed,
+            FieldCellState::Flagged => FieldCellState::Hidden,
+            FieldCellState::Hidden | FieldCellState::Questioned => FieldCellState::Flagged,
+        };
+        self.set_state(pos, state);
+        self.record_move(pos, self.state, None);
     }
 
-    pub fn get_field(&self) -> Vec<Vec<u32>> {
-        let mut i: usize = 0;
-        let len = self.cells.len();
-        let mut lines = Vec::new();
-
-        let mut line_buffer = Vec::new();
-
-        // show all if lost
-        let show_all = false;
+    /**
+     * Cycle the mark of a hidden cell: hidden -> flagged -> questioned -> hidden
+     */
+    pub fn cycle_mark(&mut self, pos: usize) {
+        if self.state.is_over() || pos >= self.cells.len() {
+            return;
+        }
+
+        let state = match self.cells[pos].state {
+            FieldCellState::Revealed => FieldCellState::Revealed,
+            FieldCellState::Hidden => FieldCellState::Flagged,
+            FieldCellState::Flagged => FieldCellState::Questioned,
+            FieldCellState::Questioned => FieldCellState::Hidden,
+        };
+        self.set_state(pos, state);
+        self.record_move(pos, self.state, None);
+    }
+
+    /**
+     * Reveal a cell and update the state of the game. Revealing an empty cell cascades to its
+     * neighbours. Does nothing once the game is over.
+     */
+    pub fn reveal_cell(&mut self, pos: usize) -> RevealOutcome {
+        if self.state.is_over() || pos >= self.cells.len() {
+            return RevealOutcome::default();
+        }
+        // clicking on a flag doesn't start the game either
+        if let FieldCellState::Flagged = self.cells[pos].state {
+            if self.config.protect_flags {
+                return RevealOutcome::default();
+            }
+        }
+
+        let state = self.state;
+        let placed_mines = if self.mines_placed {
+            None
+        } else {
+            let safe = self.first_click_safe_cells(pos);
+            self.place_mines(&safe);
+            Some(safe)
+        };
+
+        self.state = GameState::Playing;
+
+        let outcome = self.reveal(pos);
+        self.update_state(outcome.hit_mine);
+        self.record_move(pos, state, placed_mines);
+
+        outcome
+    }
+
+    /**
+     * Chord on a revealed number: if as many flags as its number surround it, reveal every other
+     * hidden cell around it. Hitting a mine means a flag was misplaced.
+     */
+    pub fn chord(&mut self, pos: usize) -> RevealOutcome {
+        let mut outcome = RevealOutcome::default();
+        if self.state != GameState::Playing {
+            return outcome;
+        }
+
+        let mines = match self.cells.get(pos) {
+            Some(FieldCell {
+                state: FieldCellState::Revealed,
+                cell_type: FieldCellType::Empty(n),
+            }) if *n > 0 => *n as usize,
+            _ => return outcome,
+        };
+
+        let flags = self
+            .neighbours(pos)
+            .filter(|&i| matches!(self.cells[i].state, FieldCellState::Flagged))
+            .count();
+        if flags != mines {
+            return outcome;
+        }
+
+        for i in self.neighbours(pos) {
+            if let FieldCellState::Hidden | FieldCellState::Questioned = self.cells[i].state {
+                outcome.append(self.reveal(i));
+            }
+        }
+        self.update_state(outcome.hit_mine);
+        self.record_move(pos, GameState::Playing, None);
+
+        outcome
+    }
+
+    /**
+     * Change the state of a cell, remembering it for the move being played
+     */
+    fn set_state(&mut self, pos: usize, state: FieldCellState) {
+        let before = self.cells[pos].state;
+        if before != state {
+            self.put_state(pos, state);
+            self.changes.push(CellChange {
+                pos,
+                before,
+                after: state,
+            });
+        }
+    }
+
+    /**
+     * Add the cells changed since the last move to the history. Moves that changed nothing aren't
+     * kept, the others make the undone moves impossible to redo.
+     */
+    fn record_move(&mut self, pos: usize, state: GameState, placed_mines: Option<HashSet<usize>>) {
+        if self.changes.is_empty() && state == self.state && placed_mines.is_none() {
+            return;
+        }
+
+        self.history.push(Move {
+            pos,
+            changes: std::mem::take(&mut self.changes),
+            states: (state, self.state),
+            placed_mines,
+        });
+        self.undone.clear();
+    }
+
+    /**
+     * Undo the last move, even one that lost the game. Returns the cell it was played on.
+     */
+    pub fn undo(&mut self) -> Option<usize> {
+        let last = self.history.pop()?;
+
+        for change in last.changes.iter().rev() {
+            self.put_state(change.pos, change.before);
+        }
+        self.state = last.states.0;
+        if last.placed_mines.is_some() {
+            // the next first reveal places them again
+            for cell in self.cells.iter_mut() {
+                cell.cell_type = FieldCellType::Empty(0);
+            }
+            self.mines.clear();
+            self.mines_placed = false;
+            self.bbbv = None;
+        }
+        self.undo_count += 1;
+
+        let pos = last.pos;
+        self.undone.push(last);
+        Some(pos)
+    }
+
+    /**
+     * Play again the last undone move. Returns the cell it was played on.
+     */
+    pub fn redo(&mut self) -> Option<usize> {
+        let next = self.undone.pop()?;
+
+        if let Some(safe) = &next.placed_mines {
+            // same seed and safe cells, same mines
+            self.place_mines(safe);
+        }
+        for change in next.changes.iter() {
+            self.put_state(change.pos, change.after);
+        }
+        self.state = next.states.1;
+
+        let pos = next.pos;
+        self.history.push(next);
+        Some(pos)
+    }
+
+    /**
+     * Indexes of the mines in increasing order, empty until they are placed
+     */
+    pub fn mine_positions(&self) -> Vec<usize> {
+        let mut mines: Vec<usize> = self.mines.iter().copied().collect();
+        mines.sort_unstable();
+        mines
+    }
+
+    /**
+     * 3BV of the board: the least number of clicks needed to clear it without flags. Each opening
+     * counts once, like every number that isn't on the edge of an opening. `None` until the mines
+     * are placed.
+     */
+    pub fn bbbv(&self) -> Option<usize> {
+        self.bbbv
+    }
+
+    fn compute_bbbv(&self) -> usize {
+        let is_zero = |pos: usize| matches!(self.cells[pos].cell_type, FieldCellType::Empty(0));
+        let mut opened = vec![false; self.cells.len()];
+        let mut clicks = 0;
+
+        for start in 0..self.cells.len() {
+            if opened[start] || !is_zero(start) {
+                continue;
+            }
+            clicks += 1;
+            opened[start] = true;
+            let mut queue = vec![start];
+            while let Some(pos) = queue.pop() {
+                for neighbour in self.neighbours(pos) {
+                    if !opened[neighbour] {
+                        opened[neighbour] = true;
+                        if is_zero(neighbour) {
+                            queue.push(neighbour);
+                        }
+                    }
+                }
+            }
+        }
+
+        let isolated = self
+            .cells
+            .iter()
+            .zip(opened.iter())
+            .filter(|(cell, &opened)| !opened && matches!(cell.cell_type, FieldCellType::Empty(_)))
+            .count();
+
+        clicks + isolated
+    }
+
+    /**
+     * Snapshot of the game, moves included, to continue it later
+     */
+    pub fn save(&self) -> SavedField {
+        SavedField {
+            seed: self.seed,
+            state: self.state,
+            undo_count: self.undo_count,
+            mines_placed: self.mines_placed,
+            cells: self
+                .cells
+                .chunks(self.config.columns)
+                .map(|row| row.iter().map(FieldCell::marker).collect())
+                .collect(),
+            config: self.config.clone(),
+            history: self.history.clone(),
+            undone: self.undone.clone(),
+        }
+    }
+
+    /**
+     * Continue a game saved with `Field::save`, checking it still makes sense as the file could
+     * have been edited
+     */
+    pub fn restore(saved: SavedField) -> Result<Field, RestoreError> {
+        let config = saved.config;
+        let size = config.rows * config.columns;
+
+        let rows = grid_rows(&saved.cells.join("\n")).map_err(|_| RestoreError::Cells)?;
+        if rows.len() != config.rows || rows[0].len() != config.columns {
+            return Err(RestoreError::Cells);
+        }
+        let mut cells = Vec::with_capacity(size);
+        for &c in rows.iter().flatten() {
+            cells.push(FieldCell::from_marker(c).ok_or(RestoreError::Cells)?);
+        }
+
+        let mines = Field::mines_of(&cells);
+        let expected_mines = if saved.mines_placed { config.mines } else { 0 };
+        if mines.len() != expected_mines {
+            return Err(RestoreError::Mines);
+        }
+
+        let in_board = |m: &Move| {
+            m.pos < size
+                && m.changes.iter().all(|change| change.pos < size)
+                && m.placed_mines.as_ref().map_or(true, |safe| {
+                    // redoing the move places the mines outside of these cells
+                    safe.iter().all(|&pos| pos < size) && size - safe.len() >= config.mines
+                })
+        };
+        if !saved
+            .history
+            .iter()
+            .chain(saved.undone.iter())
+            .all(in_board)
+        {
+            return Err(RestoreError::History);
+        }
+
+        let mut field = Field {
+            config,
+            cells,
+            mines,
+            seed: saved.seed,
+            mines_placed: saved.mines_placed,
+            state: saved.state,
+            history: saved.history,
+            undone: saved.undone,
+            changes: Vec::new(),
+            undo_count: saved.undo_count,
+            flags: 0,
+            bbbv: None,
+        };
+
+        field.compute_field();
+        field.flags = field.count_flags();
+        if field.wrong_number(&Field::numbers_of(&rows)).is_some() {
+            return Err(RestoreError::Cells);
+        }
+
+        Ok(field)
+    }
+
+    /**
+     * Whether the mines were placed from the seed on a reveal, rather than given with the board
+     * like with `Field::with_mines` or `Field::parse`
+     */
+    pub fn mines_generated(&self) -> bool {
+        self.history
+            .iter()
+            .chain(self.undone.iter())
+            .any(|m| m.placed_mines.is_some())
+    }
+
+    /**
+     * Number of moves undone during the game
+     */
+    pub fn undo_count(&self) -> usize {
+        self.undo_count
+    }
 
+    pub fn is_revealed(&self, pos: usize) -> bool {
+        matches!(
+            self.cells.get(pos),
+            Some(FieldCell {
+                state: FieldCellState::Revealed,
+                ..
+            })
+        )
+    }
+
+    fn update_state(&mut self, hit_mine: bool) {
+        if hit_mine {
+            self.state = GameState::Lost;
+        } else if self.is_cleared() {
+            self.state = GameState::Won;
+            // every mine has been found
+            let mines: Vec<usize> = self.mines.iter().copied().collect();
+            for pos in mines {
+                self.set_state(pos, FieldCellState::Flagged);
+            }
+        }
+    }
+
+    /**
+     * Reveal a cell and, if it has no mines around, keep revealing in breadth first order until
+     * numbers are reached. Uses a queue rather than recursion so big boards don't overflow the stack.
+     */
+    fn reveal(&mut self, pos: usize) -> RevealOutcome {
+        let mut outcome = RevealOutcome::default();
+        let mut queue = VecDeque::new();
+        queue.push_back(pos);
+
+        while let Some(pos) = queue.pop_front() {
+            match self.cells[pos].state {
+                FieldCellState::Revealed => continue,
+                // a flagged cell cannot be revealed, neither when clicked on nor by a cascade
+                FieldCellState::Flagged if self.config.protect_flags => continue,
+                _ => {}
+            }
+
+            self.set_state(pos, FieldCellState::Revealed);
+            outcome.opened.push(pos);
+            match self.cells[pos].cell_type {
+                FieldCellType::Mine => outcome.hit_mine = true,
+                FieldCellType::Empty(0) => {
+                    for i in self.neighbours(pos) {
+                        if !self.is_revealed(i) {
+                            queue.push_back(i);
+                        }
+                    }
+                }
+                FieldCellType::Empty(_) => {}
+            }
+        }
+
+        outcome
+    }
+
+    /**
+     * Create a field mine from a vec of strings. Used for tests.
+     */
+    #[cfg(test)]
+    pub fn from(field_text: Vec<&str>) -> Field {
+        Field::parse(&field_text.join("\n")).unwrap()
+    }
+
+    /**
+     * Positions of the mines among `cells`
+     */
+    fn mines_of(cells: &[FieldCell]) -> HashSet<usize> {
+        cells
+            .iter()
+            .enumerate()
+            .filter(|(_, cell)| matches!(cell.cell_type, FieldCellType::Mine))
+            .map(|(pos, _)| pos)
+            .collect()
+    }
+
+    /**
+     * Numbers written on the revealed cells of a grid, with their position
+     */
+    fn numbers_of(rows: &[Vec<char>]) -> Vec<(usize, u8)> {
+        rows.iter()
+            .flatten()
+            .enumerate()
+            .filter_map(|(pos, &c)| Some((pos, c.to_digit(10)? as u8)))
+            .collect()
+    }
+
+    /**
+     * First of `numbers` that doesn't match the mines around its cell, with the right number
+     */
+    fn wrong_number(&self, numbers: &[(usize, u8)]) -> Option<(usize, u8, u8)> {
+        numbers
+            .iter()
+            .find_map(|&(pos, number)| match self.cells[pos].cell_type {
+                FieldCellType::Empty(n) if n != number => Some((pos, n, number)),
+                _ => None,
+            })
+    }
+
+    /**
+     * Read a board in the middle of a game, see `grid_rows` for the layout. Each cell is one of:
+     * - `.` (or `o`) or `x`: hidden empty cell or mine, like in board files
+     * - `-` or a digit: revealed empty cell, the digit must match the mines around
+     * - `X`: revealed mine, the game is lost
+     * - `f` or `F`: flag on an empty cell or a mine
+     * - `q` or `Q`: question mark on an empty cell or a mine
+     */
+    pub fn parse(text: &str) -> Result<Field, ParseError> {
+        let rows = grid_rows(text).map_err(ParseError::Grid)?;
+        let columns = rows[0].len();
+        let mut cells = Vec::with_capacity(columns * rows.len());
+        for (y, row) in rows.iter().enumerate() {
+            for (x, &c) in row.iter().enumerate() {
+                cells.push(FieldCell::from_marker(c).ok_or(ParseError::Cell {
+                    row: y + 1,
+                    column: x + 1,
+                    found: c,
+                })?);
+            }
+        }
+
+        let mines = Field::mines_of(&cells);
+        let config = GameConfig::new(rows.len(), columns, mines.len(), FirstClick::Unsafe)
+            .map_err(ParseError::Config)?;
+        let mut field = Field {
+            config,
+            cells,
+            mines,
+            seed: 0,
+            mines_placed: true,
+            state: GameState::NotStarted,
+            history: Vec::new(),
+            undone: Vec::new(),
+            changes: Vec::new(),
+            undo_count: 0,
+            flags: 0,
+            bbbv: None,
+        };
+        field.compute_field();
+        field.flags = field.count_flags();
+
+        if let Some((pos, expected, found)) = field.wrong_number(&Field::numbers_of(&rows)) {
+            let coord = field.coord(pos);
+            return Err(ParseError::Number {
+                row: coord.y + 1,
+                column: coord.x + 1,
+                expected,
+                found,
+            });
+        }
+
+        let revealed = |cell: &FieldCell| matches!(cell.state, FieldCellState::Revealed);
+        field.state = if field
+            .cells
+            .iter()
+            .any(|cell| revealed(cell) && matches!(cell.cell_type, FieldCellType::Mine))
+        {
+            GameState::Lost
+        } else if field.cells.iter().any(revealed) {
+            // a cleared board still needs its mines flagged
+            GameState::Playing
+        } else {
+            GameState::NotStarted
+        };
+        if field.state == GameState::Playing {
+            field.update_state(false);
+            // not a move of the player
+            field.changes.clear();
+        }
+
+        Ok(field)
+    }
+
+    pub fn format(&self, show_all: bool) -> String {
+        let mut text = String::from("\n");
+        let mut i = 0usize;
+        let len = self.cells.len();
+        while i < len {
+            if i > 0 && i % self.config.columns == 0 {
+                // left padding
+                text.push('\n');
+            }
+            if i % self.config.columns == 0 {
+                // left padding
+                text.push_str("  ");
+            }
+
+            let cell = self.cells.get(i).unwrap();
+
+            text.push_str(&cell.as_unicode_str(show_all));
+
+            i += 1;
+        }
+        text.push('\n');
+        text
+    }
+
+    #[cfg(test)]
+    pub fn as_text_ascii(&self, show_all: bool) -> String {
+        let mut i = 0usize;
+        let len = self.cells.len();
+        let mut text = String::new();
         while i < len {
             if i > 0 && i % self.config.columns == 0 {
                 // new line
-                lines.push(line_buffer);
-                line_buffer = Vec::new();
+                text.push('\n');
             }
 
             let cell = self.cells.get(i).unwrap();
 
-            line_buffer.push(cell.as_number(show_all));
+            text.push_str(&cell.as_ascii_str(show_all));
 
             i += 1;
         }
 
-        // last line
-        lines.push(line_buffer);
+        text
+    }
 
-        lines
+    /**
+     * What the player gets to see of the cell at `pos`, see `FieldCell::view`.
+     * Returns `None` if `pos` is outside of the board.
+     */
+    pub fn view(&self, pos: usize, reveal_all: bool) -> Option<CellView> {
+        self.cells.get(pos).map(|cell| cell.view(reveal_all))
     }
 
+    #[cfg(test)]
     pub fn as_lines(&self, show_all: bool) -> Vec<String> {
         let mut i: usize = 0;
         let len = self.cells.len();
@@ -457,6 +1489,7 @@ impl fmt::Display for Field {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::iter::FromIterator;
 
     #[test]
     fn creates_a_field() {
@@ -538,7 +1571,7 @@ x3x3x\
 "
         );
 
-        assert_eq!(field.reveal_cell(0), false);
+        assert!(!field.reveal_cell(0).hit_mine);
 
         assert_eq!(
             field.as_text_ascii(false),
@@ -550,9 +1583,10 @@ x3x3x\
 "
         );
 
-        assert_eq!(
-            field.reveal_cell(field.config.columns * field.config.rows - 1),
-            false
+        assert!(
+            !field
+                .reveal_cell(field.config.columns * field.config.rows - 1)
+                .hit_mine
         );
 
         assert_eq!(
@@ -565,7 +1599,7 @@ x3x3x\
 "
         );
 
-        assert_eq!(field.reveal_cell(1), true);
+        assert!(field.reveal_cell(1).hit_mine);
 
         assert_eq!(
             field.as_text_ascii(false),
@@ -577,4 +1611,814 @@ x3x3x\
 "
         );
     }
+
+    #[test]
+    fn game_state_lost() {
+        let mut field = Field::from(vec!["xoo", "ooo", "ooo"]);
+
+        assert_eq!(field.state(), GameState::NotStarted);
+        assert!(!field.reveal_cell(1).hit_mine);
+        assert_eq!(field.state(), GameState::Playing);
+        assert!(field.reveal_cell(0).hit_mine);
+        assert_eq!(field.state(), GameState::Lost);
+
+        // no more input is accepted
+        assert!(!field.reveal_cell(8).hit_mine);
+        field.toggle_flag(2);
+        assert_eq!(
+            field.as_text_ascii(false),
+            "\
+x1?
+???
+???\
+"
+        );
+    }
+
+    #[test]
+    fn game_state_won() {
+        let mut field = Field::from(vec!["xox", "ooo", "xox"]);
+
+        field.toggle_flag(0);
+        assert_eq!(field.state(), GameState::NotStarted);
+        field.reveal_cell(1);
+        field.reveal_cell(3);
+        field.reveal_cell(4);
+        field.reveal_cell(5);
+        assert_eq!(field.state(), GameState::Playing);
+        field.reveal_cell(7);
+        assert_eq!(field.state(), GameState::Won);
+    }
+
+    #[test]
+    fn first_click_safe_area() {
+        let config = GameConfig::new(5, 5, 16, FirstClick::SafeArea).unwrap();
+        let mut field = Field::new(&config);
+        assert!(field.mines.is_empty());
+
+        assert!(!field.reveal_cell(12).hit_mine);
+        assert_eq!(field.mines.len(), 16);
+        for pos in &[6, 7, 8, 11, 12, 13, 16, 17, 18] {
+            assert!(!field.mines.contains(pos));
+        }
+        // every other cell is a mine
+        assert_eq!(field.state(), GameState::Won);
+    }
+
+    #[test]
+    fn first_click_safe_cell() {
+        let config = GameConfig::new(3, 3, 8, FirstClick::SafeCell).unwrap();
+        let mut field = Field::new(&config);
+
+        assert!(!field.reveal_cell(0).hit_mine);
+        assert!(!field.mines.contains(&0));
+        assert_eq!(field.state(), GameState::Won);
+    }
+
+    #[test]
+    fn first_click_unsafe() {
+        let config = GameConfig::new(3, 3, 8, FirstClick::Unsafe).unwrap();
+        let field = Field::new(&config);
+
+        // mines are already placed before any reveal
+        assert_eq!(field.mines.len(), 8);
+        assert_eq!(field.as_text_ascii(true).matches('x').count(), 8);
+    }
+
+    #[test]
+    fn chord_reveals_neighbours() {
+        let mut field = Field::from(vec!["xoo", "ooo", "ooo", "ooo"]);
+
+        field.reveal_cell(4);
+        // no flag around yet
+        assert!(!field.chord(4).hit_mine);
+        assert_eq!(
+            field.as_text_ascii(false),
+            "\
+???
+?1?
+???
+???\
+"
+        );
+
+        field.toggle_flag(0);
+        assert!(!field.chord(4).hit_mine);
+        assert_eq!(
+            field.as_text_ascii(false),
+            "\
+f1-
+11-
+---
+---\
+"
+        );
+        assert_eq!(field.state(), GameState::Won);
+    }
+
+    #[test]
+    fn chord_with_wrong_flag() {
+        let mut field = Field::from(vec!["xoo", "ooo", "ooo"]);
+
+        field.reveal_cell(4);
+        field.toggle_flag(1);
+        assert!(field.chord(4).hit_mine);
+        assert_eq!(field.state(), GameState::Lost);
+    }
+
+    #[test]
+    fn reveal_reports_opened_cells() {
+        let mut field = Field::from(vec!["ooo", "ooo", "oox"]);
+
+        assert_eq!(
+            field.reveal_cell(0),
+            RevealOutcome {
+                opened: vec![0, 1, 3, 4, 2, 5, 6, 7],
+                hit_mine: false,
+            }
+        );
+        assert_eq!(field.state(), GameState::Won);
+    }
+
+    #[test]
+    fn reveal_big_field() {
+        let line = "o".repeat(1000);
+        let mut lines = vec![line.as_str(); 1000];
+        let last_line = format!("{}x", "o".repeat(999));
+        lines[999] = &last_line;
+        let mut field = Field::from(lines);
+
+        let outcome = field.reveal_cell(0);
+        assert!(!outcome.hit_mine);
+        assert_eq!(outcome.opened.len(), 1000 * 1000 - 1);
+        assert_eq!(field.state(), GameState::Won);
+    }
+
+    #[test]
+    fn neighbours_corners_and_edges() {
+        let field = Field::from(vec!["oooo", "oooo", "oooo"]);
+        let neighbours = |pos| {
+            let mut cells: Vec<usize> = field.neighbours(pos).collect();
+            cells.sort_unstable();
+            cells
+        };
+
+        // corners
+        assert_eq!(neighbours(0), vec![1, 4, 5]);
+        assert_eq!(neighbours(3), vec![2, 6, 7]);
+        assert_eq!(neighbours(8), vec![4, 5, 9]);
+        assert_eq!(neighbours(11), vec![6, 7, 10]);
+        // edges
+        assert_eq!(neighbours(1), vec![0, 2, 4, 5, 6]);
+        assert_eq!(neighbours(4), vec![0, 1, 5, 8, 9]);
+        assert_eq!(neighbours(7), vec![2, 3, 6, 10, 11]);
+        assert_eq!(neighbours(9), vec![4, 5, 6, 8, 10]);
+        // middle
+        assert_eq!(neighbours(5), vec![0, 1, 2, 4, 6, 8, 9, 10]);
+    }
+
+    #[test]
+    fn neighbours_single_cell() {
+        let field = Field::from(vec!["o"]);
+        assert_eq!(field.neighbours(0).count(), 0);
+
+        let field = Field::from(vec!["ooo"]);
+        assert_eq!(field.neighbours(1).collect::<Vec<_>>(), vec![0, 2]);
+    }
+
+    #[test]
+    fn coord_and_index() {
+        let field = Field::from(vec!["ooo", "ooo"]);
+
+        assert_eq!(field.coord(4), Coord { x: 1, y: 1 });
+        assert_eq!(field.index(Coord { x: 2, y: 1 }), Some(5));
+        assert_eq!(field.index(Coord { x: 3, y: 0 }), None);
+        assert_eq!(field.index(Coord { x: 0, y: 2 }), None);
+    }
+
+    #[test]
+    fn mines_on_the_second_row_edges() {
+        // the mines in the first column of the second row used to miss the cells above them
+        let field = Field::from(vec!["oooo", "xoox", "oooo"]);
+
+        assert_eq!(
+            field.as_text_ascii(true),
+            "\
+1111
+x11x
+1111\
+"
+        );
+    }
+
+    #[test]
+    fn mines_in_every_corner() {
+        let field = Field::from(vec!["xoox", "oooo", "xoox"]);
+
+        assert_eq!(
+            field.as_text_ascii(true),
+            "\
+x11x
+2222
+x11x\
+"
+        );
+    }
+
+    #[test]
+    fn mines_on_every_edge() {
+        let field = Field::from(vec!["oxoo", "xoox", "ooxo"]);
+
+        assert_eq!(
+            field.as_text_ascii(true),
+            "\
+2x21
+x33x
+12x2\
+"
+        );
+    }
+
+    #[test]
+    fn config_validation() {
+        assert!(GameConfig::new(9, 9, 10, FirstClick::SafeArea).is_ok());
+        assert!(GameConfig::new(1, 2, 1, FirstClick::Unsafe).is_ok());
+        assert_eq!(
+            GameConfig::new(0, 9, 10, FirstClick::SafeArea).err(),
+            Some(ConfigError::ZeroDimensions {
+                rows: 0,
+                columns: 9
+            })
+        );
+        assert_eq!(
+            GameConfig::new(9, 9, 81, FirstClick::SafeArea).err(),
+            Some(ConfigError::TooManyMines {
+                mines: 81,
+                cells: 81
+            })
+        );
+        assert_eq!(
+            GameConfig::new(9, MAX_BOARD_SIDE + 1, 10, FirstClick::SafeArea).err(),
+            Some(ConfigError::BoardTooLarge {
+                rows: 9,
+                columns: MAX_BOARD_SIDE + 1
+            })
+        );
+    }
+
+    #[test]
+    fn same_seed_same_board() {
+        let config = GameConfig::new(16, 30, 99, FirstClick::Unsafe).unwrap();
+        let field = Field::with_seed(&config, 42);
+
+        assert_eq!(field.seed(), 42);
+        assert_eq!(
+            field.as_text_ascii(true),
+            Field::with_seed(&config, 42).as_text_ascii(true)
+        );
+        assert_ne!(
+            field.as_text_ascii(true),
+            Field::with_seed(&config, 43).as_text_ascii(true)
+        );
+    }
+
+    #[test]
+    fn same_seed_same_board_after_first_click() {
+        let mut field = Field::with_seed(&CONFIG_EXPERT, 7);
+        let mut other = Field::with_seed(&CONFIG_EXPERT, 7);
+
+        field.reveal_cell(100);
+        other.reveal_cell(100);
+        assert_eq!(field.as_text_ascii(true), other.as_text_ascii(true));
+    }
+
+    #[test]
+    fn seeds_give_pinned_boards() {
+        // changing the generator would change the boards of the shared seeds and replays
+        let config = GameConfig::new(9, 9, 10, FirstClick::SafeArea).unwrap();
+        let mut field = Field::with_seed(&config, 0);
+        field.reveal_cell(40);
+        assert_eq!(
+            field.mine_positions(),
+            vec![12, 28, 42, 44, 56, 57, 62, 65, 67, 71]
+        );
+    }
+
+    #[test]
+    fn remaining_mines() {
+        let mut field = Field::from(vec!["xoo", "ooo", "oox"]);
+
+        assert_eq!(field.remaining_mines(), 2);
+        field.toggle_flag(0);
+        assert_eq!(field.remaining_mines(), 1);
+        field.toggle_flag(1);
+        field.toggle_flag(2);
+        assert_eq!(field.remaining_mines(), -1);
+        field.toggle_flag(2);
+        assert_eq!(field.remaining_mines(), 0);
+    }
+
+    #[test]
+    fn cell_views() {
+        let mut field = Field::from(vec!["xxx", "xox", "xxo"]);
+
+        assert_eq!(field.view(4, false), Some(CellView::Hidden));
+        assert_eq!(field.view(4, true), Some(CellView::Revealed(7)));
+        assert_eq!(field.view(0, true), Some(CellView::Mine));
+        assert_eq!(field.view(9, false), None);
+        assert_eq!(field.view(usize::MAX, true), None);
+
+        field.toggle_flag(0);
+        field.toggle_flag(8);
+        assert_eq!(field.view(0, false), Some(CellView::Flagged));
+        assert_eq!(field.view(0, true), Some(CellView::Flagged));
+        assert_eq!(field.view(8, false), Some(CellView::Flagged));
+        assert_eq!(field.view(8, true), Some(CellView::WrongFlag));
+
+        field.reveal_cell(4);
+        assert_eq!(field.view(4, false), Some(CellView::Revealed(7)));
+        field.reveal_cell(1);
+        assert_eq!(field.view(1, false), Some(CellView::ExplodedMine));
+    }
+
+    #[test]
+    fn eight_is_a_number_not_a_flag() {
+        let mut field = Field::from(vec!["xxx", "xox", "xxx"]);
+
+        field.reveal_cell(4);
+        assert_eq!(field.view(4, false), Some(CellView::Revealed(8)));
+    }
+
+    #[test]
+    fn mines_are_flagged_when_won() {
+        let mut field = Field::from(vec!["xoo", "ooo", "oox"]);
+
+        field.reveal_cell(2);
+        field.reveal_cell(6);
+        assert_eq!(field.state(), GameState::Won);
+        assert_eq!(field.view(0, false), Some(CellView::Flagged));
+        assert_eq!(field.view(8, false), Some(CellView::Flagged));
+        assert_eq!(field.remaining_mines(), 0);
+    }
+
+    #[test]
+    fn board_view_when_lost() {
+        let mut field = Field::from(vec!["xoo", "ooo", "oxx"]);
+
+        field.toggle_flag(2);
+        field.toggle_flag(8);
+        field.reveal_cell(7);
+        assert_eq!(field.state(), GameState::Lost);
+
+        let views: Vec<CellView> = (0..9).filter_map(|pos| field.view(pos, true)).collect();
+        assert_eq!(
+            views,
+            vec![
+                CellView::Mine,
+                CellView::Revealed(1),
+                CellView::WrongFlag,
+                CellView::Revealed(2),
+                CellView::Revealed(3),
+                CellView::Revealed(2),
+                CellView::Revealed(1),
+                CellView::ExplodedMine,
+                CellView::Flagged,
+            ]
+        );
+    }
+
+    #[test]
+    fn question_marks() {
+        let mut field = Field::from(vec!["xoo", "ooo", "ooo"]);
+
+        field.cycle_mark(0);
+        assert_eq!(field.view(0, false), Some(CellView::Flagged));
+        assert_eq!(field.remaining_mines(), 0);
+        field.cycle_mark(0);
+        assert_eq!(field.view(0, false), Some(CellView::Questioned));
+        // not a flag
+        assert_eq!(field.remaining_mines(), 1);
+        field.cycle_mark(0);
+        assert_eq!(field.view(0, false), Some(CellView::Hidden));
+
+        field.cycle_mark(1);
+        field.cycle_mark(1);
+        assert_eq!(
+            field.as_text_ascii(false),
+            "\
+?q?
+???
+???\
+"
+        );
+        assert_eq!(field.as_lines(false), vec!["🔲❓🔲", "🔲🔲🔲", "🔲🔲🔲"]);
+        assert_eq!(field.view(1, true), Some(CellView::Revealed(1)));
+
+        // questioned cells can still be revealed
+        assert!(!field.reveal_cell(1).hit_mine);
+        assert_eq!(field.view(1, false), Some(CellView::Revealed(1)));
+    }
+
+    #[test]
+    fn marks_do_not_apply_to_revealed_cells() {
+        let mut field = Field::from(vec!["xoo", "ooo", "ooo"]);
+
+        field.reveal_cell(1);
+        field.toggle_flag(1);
+        field.cycle_mark(1);
+        assert_eq!(field.view(1, false), Some(CellView::Revealed(1)));
+    }
+
+    #[test]
+    fn chord_reveals_questioned_cells() {
+        let mut field = Field::from(vec!["xoo", "ooo", "ooo"]);
+
+        field.reveal_cell(4);
+        field.toggle_flag(0);
+        field.cycle_mark(1);
+        field.cycle_mark(1);
+        field.chord(4);
+        assert_eq!(field.view(1, false), Some(CellView::Revealed(1)));
+        assert_eq!(field.state(), GameState::Won);
+    }
+
+    #[test]
+    fn flagged_cells_are_not_revealed() {
+        let mut field = Field::from(vec!["xoo", "ooo", "ooo"]);
+
+        field.toggle_flag(0);
+        assert_eq!(field.reveal_cell(0), RevealOutcome::default());
+        assert_eq!(field.view(0, false), Some(CellView::Flagged));
+        assert_eq!(field.state(), GameState::NotStarted);
+
+        field.toggle_flag(1);
+        assert!(field.reveal_cell(1).opened.is_empty());
+        assert_eq!(field.view(1, false), Some(CellView::Flagged));
+    }
+
+    #[test]
+    fn cascade_stops_at_flags() {
+        let mut field = Field::from(vec!["ooooo", "ooooo", "ooooo", "oooox"]);
+
+        // a wrong flag in the middle of the opening and one on its border
+        field.toggle_flag(7);
+        field.toggle_flag(13);
+        let outcome = field.reveal_cell(0);
+        assert!(!outcome.hit_mine);
+        assert!(!outcome.opened.contains(&7));
+        assert!(!outcome.opened.contains(&13));
+        assert_eq!(
+            field.as_text_ascii(false),
+            "\
+-----
+--f--
+---f1
+---1?\
+"
+        );
+        assert_eq!(field.remaining_mines(), -1);
+        assert_eq!(field.state(), GameState::Playing);
+
+        // removing the flags lets them be revealed again
+        field.toggle_flag(7);
+        field.reveal_cell(7);
+        field.toggle_flag(13);
+        field.reveal_cell(13);
+        assert_eq!(field.state(), GameState::Won);
+    }
+
+    #[test]
+    fn cascade_clears_flags_without_protection() {
+        let mut field = Field::from(vec!["ooooo", "ooooo", "ooooo", "oooox"]);
+        field.config = field.config.clone().with_flag_protection(false);
+
+        field.toggle_flag(7);
+        field.toggle_flag(13);
+        let outcome = field.reveal_cell(0);
+        assert!(outcome.opened.contains(&7));
+        assert!(outcome.opened.contains(&13));
+        assert_eq!(field.state(), GameState::Won);
+
+        let mut field = Field::from(vec!["xoo", "ooo", "ooo"]);
+        field.config = field.config.clone().with_flag_protection(false);
+        field.toggle_flag(0);
+        assert!(field.reveal_cell(0).hit_mine);
+        assert_eq!(field.state(), GameState::Lost);
+    }
+
+    #[test]
+    fn undo_and_redo() {
+        let mut field = Field::from(vec!["xoo", "ooo", "oox"]);
+
+        field.reveal_cell(2);
+        field.toggle_flag(0);
+        field.reveal_cell(6);
+        assert_eq!(field.state(), GameState::Won);
+        let won = field.as_text_ascii(false);
+        assert_eq!(
+            won,
+            "\
+f1-
+121
+-1f\
+"
+        );
+
+        assert_eq!(field.undo(), Some(6));
+        // the flag placed by the win is removed, not the one placed by the player
+        assert_eq!(
+            field.as_text_ascii(false),
+            "\
+f1-
+?21
+???\
+"
+        );
+        assert_eq!(field.state(), GameState::Playing);
+        assert_eq!(field.undo(), Some(0));
+        assert_eq!(field.undo(), Some(2));
+        assert_eq!(field.undo(), None);
+        assert_eq!(field.state(), GameState::NotStarted);
+        assert_eq!(field.undo_count(), 3);
+
+        assert_eq!(field.redo(), Some(2));
+        assert_eq!(field.redo(), Some(0));
+        assert_eq!(field.redo(), Some(6));
+        assert_eq!(field.redo(), None);
+        assert_eq!(field.as_text_ascii(false), won);
+        assert_eq!(field.state(), GameState::Won);
+        assert_eq!(field.undo_count(), 3);
+    }
+
+    #[test]
+    fn undo_a_lost_game() {
+        let mut field = Field::from(vec!["xoo", "ooo", "oox"]);
+
+        field.reveal_cell(2);
+        field.reveal_cell(0);
+        assert_eq!(field.state(), GameState::Lost);
+        assert_eq!(field.view(0, false), Some(CellView::ExplodedMine));
+
+        field.undo();
+        assert_eq!(field.state(), GameState::Playing);
+        assert_eq!(field.view(0, false), Some(CellView::Hidden));
+
+        // a new move drops the undone ones
+        field.toggle_flag(0);
+        assert_eq!(field.redo(), None);
+        assert_eq!(field.view(0, false), Some(CellView::Flagged));
+    }
+
+    #[test]
+    fn undo_chord_and_marks() {
+        let mut field = Field::from(vec!["xoo", "ooo", "ooo"]);
+
+        field.reveal_cell(1);
+        field.cycle_mark(0);
+        field.cycle_mark(0);
+        assert_eq!(field.view(0, false), Some(CellView::Questioned));
+        field.undo();
+        assert_eq!(field.view(0, false), Some(CellView::Flagged));
+
+        field.chord(1);
+        assert_eq!(field.state(), GameState::Won);
+        field.undo();
+        assert_eq!(field.state(), GameState::Playing);
+        assert_eq!(
+            field.as_text_ascii(false),
+            "\
+f1?
+???
+???\
+"
+        );
+
+        // moves that change nothing aren't recorded
+        field.toggle_flag(1);
+        field.reveal_cell(1);
+        field.undo();
+        assert_eq!(field.view(0, false), Some(CellView::Hidden));
+        field.undo();
+        assert_eq!(field.view(1, false), Some(CellView::Hidden));
+    }
+
+    #[test]
+    fn undo_first_reveal_places_mines_again() {
+        let config = GameConfig::new(16, 30, 99, FirstClick::SafeArea).unwrap();
+        let mut field = Field::with_seed(&config, 7);
+
+        field.reveal_cell(40);
+        let board = field.as_text_ascii(true);
+        let opened = field.as_text_ascii(false);
+
+        field.undo();
+        assert_eq!(field.state(), GameState::NotStarted);
+        field.redo();
+        assert_eq!(field.as_text_ascii(true), board);
+        assert_eq!(field.as_text_ascii(false), opened);
+
+        // another first reveal is still safe
+        field.undo();
+        assert!(!field.reveal_cell(0).hit_mine);
+    }
+
+    #[test]
+    fn parse_mid_game() {
+        let mut field = Field::parse(
+            "
+oxo-
+of1-
+Qo0-
+",
+        )
+        .unwrap();
+
+        assert_eq!(field.config.rows(), 3);
+        assert_eq!(field.config.columns(), 4);
+        assert_eq!(field.config.mines(), 2);
+        assert_eq!(field.state(), GameState::Playing);
+        assert_eq!(field.view(3, false), Some(CellView::Revealed(0)));
+        assert_eq!(field.view(5, false), Some(CellView::Flagged));
+        assert_eq!(field.view(5, true), Some(CellView::WrongFlag));
+        assert_eq!(field.view(8, false), Some(CellView::Questioned));
+        assert_eq!(field.view(8, true), Some(CellView::Mine));
+        assert_eq!(field.remaining_mines(), 1);
+        assert_eq!(
+            field.as_text_ascii(false),
+            "\
+???-
+?f1-
+q?--\
+"
+        );
+
+        // a questioned mine is still a mine
+        assert!(field.reveal_cell(8).hit_mine);
+    }
+
+    #[test]
+    fn parse_finished_games() {
+        let field = Field::parse("X1\n11\n").unwrap();
+        assert_eq!(field.state(), GameState::Lost);
+        assert_eq!(field.view(0, false), Some(CellView::ExplodedMine));
+
+        let mut field = Field::parse("x1\n11").unwrap();
+        assert_eq!(field.state(), GameState::Won);
+        assert_eq!(field.view(0, false), Some(CellView::Flagged));
+        // the flags of the win can't be undone
+        assert_eq!(field.undo(), None);
+
+        let field = Field::parse("xo\noo").unwrap();
+        assert_eq!(field.state(), GameState::NotStarted);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            Field::parse("").err(),
+            Some(ParseError::Grid(GridError::Empty))
+        );
+        assert_eq!(
+            Field::parse("\n  \n").err(),
+            Some(ParseError::Grid(GridError::Empty))
+        );
+        assert_eq!(
+            Field::parse("oxo\nox").err(),
+            Some(ParseError::Grid(GridError::RowLength {
+                row: 2,
+                expected: 3,
+                found: 2
+            }))
+        );
+        assert_eq!(
+            Field::parse("oxo\no#o").err(),
+            Some(ParseError::Cell {
+                row: 2,
+                column: 2,
+                found: '#'
+            })
+        );
+        assert_eq!(
+            Field::parse("xo\no2").err(),
+            Some(ParseError::Number {
+                row: 2,
+                column: 2,
+                expected: 1,
+                found: 2
+            })
+        );
+        assert!(matches!(
+            Field::parse("xx\nxX").err(),
+            Some(ParseError::Config(ConfigError::TooManyMines { .. }))
+        ));
+    }
+
+    #[test]
+    fn bbbv() {
+        assert_eq!(Field::new(&CONFIG_BEGINNER).bbbv(), None);
+
+        // two openings covering every number
+        let field = Field::parse(
+            "xooo
+oooo
+ooox",
+        )
+        .unwrap();
+        assert_eq!(field.bbbv(), Some(2));
+        // an opening and a number next to no opening
+        assert_eq!(Field::parse("xoxoo").unwrap().bbbv(), Some(2));
+        // only numbers
+        assert_eq!(
+            Field::parse(
+                "xo
+oo"
+            )
+            .unwrap()
+            .bbbv(),
+            Some(3)
+        );
+        // progress doesn't change it
+        assert_eq!(
+            Field::parse(
+                "x100
+1111
+001x"
+            )
+            .unwrap()
+            .bbbv(),
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn configs_are_checked_when_deserialized() {
+        let config: GameConfig = toml::from_str(
+            "rows = 9\ncolumns = 9\nmines = 10\nfirst_click = \"SafeCell\"\nprotect_flags = false",
+        )
+        .unwrap();
+        assert_eq!(config.mines(), 10);
+        assert!(!config.protect_flags());
+
+        assert!(toml::from_str::<GameConfig>(
+            "rows = 2\ncolumns = 2\nmines = 4\nfirst_click = \"SafeCell\"\nprotect_flags = true",
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn restore_checks_the_moves() {
+        let config = GameConfig::new(4, 4, 2, FirstClick::SafeArea).unwrap();
+        let mut field = Field::with_seed(&config, 3);
+        field.reveal_cell(0);
+        assert!(Field::restore(field.save()).is_ok());
+
+        // redoing the first reveal couldn't place the mines anymore
+        let mut saved = field.save();
+        saved.history[0].placed_mines = Some((0..15).collect());
+        assert_eq!(Field::restore(saved).err(), Some(RestoreError::History));
+
+        let mut saved = field.save();
+        saved.history[0].pos = 16;
+        assert_eq!(Field::restore(saved).err(), Some(RestoreError::History));
+    }
+
+    #[test]
+    fn flag_count_follows_the_moves() {
+        let config = GameConfig::new(9, 9, 10, FirstClick::SafeArea).unwrap();
+        let mut field = Field::with_seed(&config, 5);
+        field.reveal_cell(40);
+        let hidden: Vec<usize> = (0..81)
+            .filter(|&pos| field.view(pos, false) == Some(CellView::Hidden))
+            .take(2)
+            .collect();
+        field.toggle_flag(hidden[0]);
+        field.toggle_flag(hidden[1]);
+        assert_eq!(field.remaining_mines(), 8);
+
+        field.undo();
+        assert_eq!(field.remaining_mines(), 9);
+        field.redo();
+        field.cycle_mark(hidden[0]);
+        assert_eq!(field.remaining_mines(), 9);
+
+        let restored = Field::restore(field.save()).unwrap();
+        assert_eq!(restored.remaining_mines(), 9);
+        assert_eq!(Field::parse("xF\nff").unwrap().remaining_mines(), -1);
+    }
+
+    #[test]
+    fn bbbv_follows_undo_and_restore() {
+        let config = GameConfig::new(9, 9, 10, FirstClick::SafeArea).unwrap();
+        let mut field = Field::with_seed(&config, 5);
+        assert_eq!(field.bbbv(), None);
+        field.reveal_cell(40);
+        let bbbv = field.bbbv();
+        assert!(bbbv.is_some());
+
+        field.undo();
+        assert_eq!(field.bbbv(), None);
+        field.redo();
+        assert_eq!(field.bbbv(), bbbv);
+        assert_eq!(Field::restore(field.save()).unwrap().bbbv(), bbbv);
+    }
 }
@@ -1,12 +1,23 @@
 use argh::FromArgs;
-use std::{error::Error, io, time::Duration};
-use termion::{event::Key, input::MouseTerminal, raw::IntoRawMode, screen::AlternateScreen};
+use std::{
+    error::Error,
+    io,
+    path::Path,
+    time::{Duration, SystemTime},
+};
+use termion::{event::Key, input::MouseTerminal, raw::IntoRawMode};
 use tui::{backend::TermionBackend, Terminal};
 
 mod app;
+mod board;
+mod replay;
+mod save;
+mod theme;
 mod ui;
 mod util;
 use app::App;
+use save::SavedGame;
+use theme::Theme;
 use util::event::{Config, Event, Events};
 mod game;
 
@@ -20,11 +31,182 @@ struct Options {
     /// whether unicode symbols are used to improve the overall look of the app
     #[argh(option, default = "true", short = 'u')]
     enhanced_graphics: bool,
+    /// cells kept free of mines on the first reveal: unsafe, cell or area
+    #[argh(option, default = "game::FirstClick::SafeArea")]
+    first_click: game::FirstClick,
+    /// board preset: beginner, intermediate or expert
+    #[argh(option, default = "game::Difficulty::Beginner", short = 'd')]
+    difficulty: game::Difficulty,
+    /// number of rows, overrides the difficulty
+    #[argh(option)]
+    rows: Option<usize>,
+    /// number of columns, overrides the difficulty
+    #[argh(option)]
+    columns: Option<usize>,
+    /// number of mines, overrides the difficulty
+    #[argh(option)]
+    mines: Option<usize>,
+    /// theme of the minefield: classic, ascii, high-contrast or colour-blind. Defaults to
+    /// classic, or ascii when enhanced graphics are disabled
+    #[argh(option)]
+    theme: Option<String>,
+    /// path to a TOML theme file, takes precedence over --theme
+    #[argh(option)]
+    theme_file: Option<String>,
+    /// whether marking a cell cycles through a question mark after the flag
+    #[argh(option, default = "true")]
+    question_marks: bool,
+    /// whether flagged cells are protected from being revealed by a click or a cascade
+    #[argh(option, default = "true")]
+    protect_flags: bool,
+    /// seed used to place the mines, to replay the same board
+    #[argh(option, short = 's')]
+    seed: Option<u64>,
+    /// continue the game left in progress when quitting last time
+    #[argh(switch)]
+    resume: bool,
+    /// path to a replay to watch, the board is read-only. Space pauses, h and l step back and
+    /// forth, k and j change the speed and r starts over
+    #[argh(option)]
+    replay: Option<String>,
+    /// path to a board to play instead of a generated one: a text grid of `x` and `.`, or an
+    /// .mbf file
+    #[argh(option)]
+    board: Option<String>,
+    /// path where the board of the game is written on quit, as an .mbf file or a text grid. The
+    /// mines are placed on the first reveal, quitting before it is an error
+    #[argh(option)]
+    export_board: Option<String>,
+    /// path to a game to continue from, a grid with `.` and `x` for hidden cells like in board
+    /// files, `-` and digits for revealed ones, `f` and `F` for flags and `q` and `Q` for question
+    /// marks. Capitals are mines. The game isn't recorded as a replay.
+    #[argh(option)]
+    puzzle: Option<String>,
+}
+
+/// Build the game config from the difficulty and the custom values passed on the command line
+fn game_config(options: &Options) -> Result<game::GameConfig, String> {
+    let preset = options.difficulty.config();
+    game::GameConfig::new(
+        options.rows.unwrap_or_else(|| preset.rows()),
+        options.columns.unwrap_or_else(|| preset.columns()),
+        options.mines.unwrap_or_else(|| preset.mines()),
+        options.first_click,
+    )
+    .map(|config| config.with_flag_protection(options.protect_flags))
+    .map_err(|err| err.to_string())
+}
+
+fn theme(options: &Options) -> Result<Theme, String> {
+    if let Some(path) = &options.theme_file {
+        return Theme::from_file(Path::new(path)).map_err(|err| err.to_string());
+    }
+
+    let name = match &options.theme {
+        Some(name) => name.as_str(),
+        None if options.enhanced_graphics => "classic",
+        None => "ascii",
+    };
+    Theme::builtin(name).ok_or_else(|| theme::ThemeError::UnknownTheme(name.into()).to_string())
+}
+
+/// Keep the replay of a finished game in the data dir, named after when it ended and its seed
+fn save_replay(replay: &replay::Replay) -> Result<(), replay::ReplayError> {
+    let dir = match save::data_dir() {
+        Some(dir) => dir.join("replays"),
+        None => return Ok(()),
+    };
+    let ended = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default();
+
+    replay.write(&dir.join(format!("{}-{}.replay", ended.as_secs(), replay.seed)))
+}
+
+/// Where the game comes from when it isn't generated
+enum Start {
+    Resume(game::Field, Duration, Option<replay::Recording>),
+    Replay(replay::Replay),
+    Board(board::Board),
+    Puzzle(game::Field),
+}
+
+fn start(options: &Options) -> Result<Option<Start>, String> {
+    let sources = [
+        options.resume,
+        options.replay.is_some(),
+        options.board.is_some(),
+        options.puzzle.is_some(),
+    ];
+    if sources.iter().filter(|&&source| source).count() > 1 {
+        return Err("only one of --resume, --replay, --board and --puzzle can be used".into());
+    }
+
+    if options.resume {
+        let (field, elapsed, recording) = resume()?;
+        return Ok(Some(Start::Resume(field, elapsed, recording)));
+    }
+    if let Some(path) = &options.replay {
+        let replay = replay::Replay::read(Path::new(path)).map_err(|err| err.to_string())?;
+        return Ok(Some(Start::Replay(replay)));
+    }
+    if let Some(path) = &options.board {
+        let board = board::Board::read(Path::new(path)).map_err(|err| err.to_string())?;
+        return Ok(Some(Start::Board(board)));
+    }
+    if let Some(path) = &options.puzzle {
+        return Ok(Some(Start::Puzzle(puzzle(
+            Path::new(path),
+            options.protect_flags,
+        )?)));
+    }
+
+    Ok(None)
+}
+
+fn puzzle(path: &Path, protect_flags: bool) -> Result<game::Field, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|err| format!("could not read the puzzle file: {}", err))?;
+    let mut field = game::Field::parse(&text).map_err(|err| err.to_string())?;
+    field.config = field.config.clone().with_flag_protection(protect_flags);
+
+    Ok(field)
+}
+
+/// Load the game saved on quit, it is removed so that it can only be resumed once
+fn resume() -> Result<(game::Field, Duration, Option<replay::Recording>), String> {
+    let path = save::save_path().ok_or("no data directory to find the saved game in")?;
+    if !path.exists() {
+        return Err("there is no saved game to resume".into());
+    }
+    let game = SavedGame::read(&path)
+        .and_then(SavedGame::into_game)
+        .map_err(|err| err.to_string())?;
+    std::fs::remove_file(&path).map_err(|err| err.to_string())?;
+
+    Ok(game)
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let game_config = game::CONFIG_BEGINNER;
     let options: Options = argh::from_env();
+    let (game_config, theme, start) = match game_config(&options).and_then(|config| {
+        let theme = theme(&options)?;
+        let start = start(&options)?;
+        let config = match &start {
+            Some(Start::Board(board)) => board
+                .config()
+                .map_err(|err| err.to_string())?
+                .with_flag_protection(options.protect_flags),
+            _ => config,
+        };
+        Ok((config, theme, start))
+    }) {
+        Ok(config_and_theme) => config_and_theme,
+        Err(message) => {
+            eprintln!("Error: {}", message);
+            std::process::exit(1);
+        }
+    };
 
     let events = Events::with_config(Config {
         tick_rate: Duration::from_millis(options.tick_rate),
@@ -38,42 +220,80 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     terminal.clear()?;
 
-    let mut app = App::new("MineSweeper", &game_config, options.enhanced_graphics);
+    let mut app = App::new(
+        "MineSweeper",
+        &game_config,
+        options.seed,
+        options.enhanced_graphics,
+        theme,
+        options.question_marks,
+    );
+    match start {
+        Some(Start::Resume(field, elapsed, recording)) => app.resume(field, elapsed, recording),
+        Some(Start::Replay(replay)) => app.play(replay),
+        Some(Start::Board(board)) => app.set_board(board.mines),
+        Some(Start::Puzzle(field)) => app.set_puzzle(field),
+        None => {}
+    }
     loop {
         terminal.draw(|f| ui::draw(f, &mut app))?;
 
         match events.next()? {
-            Event::Input(key) => match key {
-                //   Key::Up | Key::Char('k') => {
-                //     app.on_up();
-                //   }
-                //   Key::Down | Key::Char('j') => {
-                //     app.on_down();
-                //   }
-                //   Key::Left | Key::Char('h') => {
-                //     app.on_left();
-                //   }
-                //   Key::Right | Key::Char('l') => {
-                //     app.on_right();
-                //   }
-                Key::Char(c) => {
-                    app.on_key(c);
-                }
-                _ => {}
-            },
+            Event::Input(Key::Up) => {
+                app.on_up();
+            }
+            Event::Input(Key::Down) => {
+                app.on_down();
+            }
+            Event::Input(Key::Left) => {
+                app.on_left();
+            }
+            Event::Input(Key::Right) => {
+                app.on_right();
+            }
+            Event::Input(Key::Ctrl('r')) => {
+                app.on_redo();
+            }
+            Event::Input(Key::Char(c)) => {
+                app.on_key(c);
+            }
             Event::Click(button, x, y) => {
                 app.on_click(button, x, y);
             }
-            // Event::Tick => {
-            //   app.on_tick();
-            // }
+            Event::Tick => {
+                app.on_tick();
+            }
             _ => {}
         }
 
+        if let Some(replay) = app.take_finished_replay() {
+            // losing the replay isn't worth interrupting the game
+            let _ = save_replay(&replay);
+        }
+
         if app.should_quit {
             break;
         }
     }
 
+    // keep the game for later unless it's over or hasn't started
+    if app.playback.is_none() && app.field.state() == game::GameState::Playing {
+        if let Some(path) = save::save_path() {
+            SavedGame::new(&app.field, app.elapsed, app.recording().as_ref()).write(&path)?;
+        }
+    }
+
+    if let Some(path) = &options.export_board {
+        let board = board::Board::from_field(&app.field);
+        // the mines are only placed on the first reveal
+        if board.mines.len() != app.field.config.mines() {
+            // leave the raw mode before writing the message
+            drop(terminal);
+            eprintln!("Error: the board has no mines to export until a cell is revealed");
+            std::process::exit(1);
+        }
+        board.write(Path::new(path))?;
+    }
+
     Ok(())
 }
@@ -0,0 +1,953 @@
+use crate::game::{CellView, ConfigError, Coord, Field, FirstClick, GameConfig, GameState};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+/// Version of the replay format, written on the first line of every replay
+pub const REPLAY_VERSION: u32 = 1;
+
+/// First word of a replay file
+const MAGIC: &str = "minesweeper-replay";
+
+/// Something the player did, with the cell it targeted
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    Reveal(Coord),
+    Flag(Coord),
+    /// Remove a flag or a question mark
+    Unflag(Coord),
+    Question(Coord),
+    Chord(Coord),
+    /// The keyboard cursor moved, clicks move it on their own
+    Cursor(Coord),
+    Undo,
+    Redo,
+}
+
+impl Action {
+    /**
+     * Cell targeted by the action, if any
+     */
+    pub fn coord(&self) -> Option<Coord> {
+        match *self {
+            Action::Reveal(coord)
+            | Action::Flag(coord)
+            | Action::Unflag(coord)
+            | Action::Question(coord)
+            | Action::Chord(coord)
+            | Action::Cursor(coord) => Some(coord),
+            Action::Undo | Action::Redo => None,
+        }
+    }
+}
+
+/// An action and when it happened, since the start of the game
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Step {
+    pub time: Duration,
+    pub action: Action,
+}
+
+impl Step {
+    /**
+     * Read a step written as a line of a replay, see `Replay`
+     */
+    pub fn parse(line: &str) -> Result<Step, String> {
+        let mut words = line.split_whitespace();
+        let time = words
+            .next()
+            .and_then(|time| time.parse().ok())
+            .map(Duration::from_millis)
+            .ok_or("expected the time of the action in milliseconds")?;
+        let action = parse_action(words)?;
+        Ok(Step { time, action })
+    }
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ", self.time.as_millis())?;
+        let (name, coord) = match self.action {
+            Action::Reveal(coord) => ("reveal", Some(coord)),
+            Action::Flag(coord) => ("flag", Some(coord)),
+            Action::Unflag(coord) => ("unflag", Some(coord)),
+            Action::Question(coord) => ("question", Some(coord)),
+            Action::Chord(coord) => ("chord", Some(coord)),
+            Action::Cursor(coord) => ("cursor", Some(coord)),
+            Action::Undo => ("undo", None),
+            Action::Redo => ("redo", None),
+        };
+        match coord {
+            Some(coord) => write!(f, "{} {} {}", name, coord.x, coord.y),
+            None => write!(f, "{}", name),
+        }
+    }
+}
+
+/// Actions of a game still in progress, kept with the game when it's saved
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recording {
+    /// Time since the recording started, the time of the next step starts from there
+    pub time: Duration,
+    pub steps: Vec<Step>,
+}
+
+/// How a game ended, to compare it with other games
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GameResult {
+    pub won: bool,
+    /// Time from the first reveal to the end of the game
+    pub time: Duration,
+    /// 3BV of the board, see `Field::bbbv`
+    pub bbbv: usize,
+    /// Number of moves undone during the game, see `Field::undo_count`
+    pub undos: usize,
+}
+
+impl GameResult {
+    /**
+     * Result of a field whose game is over
+     */
+    pub fn new(field: &Field, time: Duration) -> GameResult {
+        GameResult {
+            won: field.state() == GameState::Won,
+            time,
+            bbbv: field.bbbv().unwrap_or_default(),
+            undos: field.undo_count(),
+        }
+    }
+
+    /**
+     * 3BV cleared per second, the usual measure of speed between boards of different difficulty.
+     * Uses the time in whole milliseconds, as written in replays, so it reads back the same.
+     */
+    pub fn bbbv_per_second(&self) -> f64 {
+        let millis = self.time.as_millis();
+        if millis > 0 {
+            self.bbbv as f64 * 1000.0 / millis as f64
+        } else {
+            0.0
+        }
+    }
+}
+
+/**
+ * Everything needed to play a game again: the board and the actions of the player.
+ *
+ * The text format has one entry per line, blank lines and lines starting with `#` are ignored:
+ *
+ * ```text
+ * minesweeper-replay 1
+ * config rows=9 columns=9 mines=10 first-click=area protect-flags=true
+ * seed 42
+ * board generated
+ * mines 3,0 7,1 ...
+ * result won time=25300 3bv=17 undos=2 3bv/s=0.67
+ * 0 cursor 1 0
+ * 1250 reveal 1 0
+ * 3020 flag 0 1
+ * ```
+ *
+ * A `generated` board is played back from the seed, so undoing the first reveal and revealing
+ * another cell places the mines again like during the game. A `fixed` board, loaded from a file
+ * or a puzzle, is played back on its mines, which is also the default without a board line.
+ *
+ * Mines are `x,y` coordinates and each action starts with its time in milliseconds. The result is
+ * only there for finished games, its 3BV/s is written for other tools and ignored when reading, a
+ * missing number of undos counts as none.
+ */
+#[derive(Debug, Clone)]
+pub struct Replay {
+    pub config: GameConfig,
+    pub seed: u64,
+    /// Whether the mines come from the seed, see `Field::mines_generated`
+    pub generated: bool,
+    /// Indexes of the mines on the board
+    pub mines: Vec<usize>,
+    /// How the game ended, if it did
+    pub result: Option<GameResult>,
+    pub steps: Vec<Step>,
+}
+
+impl Replay {
+    /**
+     * Replay of a game played on `field`, which must have its mines placed
+     */
+    pub fn new(field: &Field, steps: Vec<Step>) -> Replay {
+        Replay {
+            config: field.config.clone(),
+            seed: field.seed(),
+            generated: field.mines_generated(),
+            mines: field.mine_positions(),
+            result: None,
+            steps,
+        }
+    }
+
+    pub fn read(path: &Path) -> Result<Replay, ReplayError> {
+        let text = fs::read_to_string(path).map_err(ReplayError::Io)?;
+        Replay::parse(&text)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), ReplayError> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(ReplayError::Io)?;
+        }
+        fs::write(path, self.to_string()).map_err(ReplayError::Io)
+    }
+
+    pub fn parse(text: &str) -> Result<Replay, ReplayError> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+
+        match lines.next() {
+            Some((_, line)) => {
+                let mut words = line.split_whitespace();
+                if words.next() != Some(MAGIC) {
+                    return Err(ReplayError::NotAReplay);
+                }
+                let version = words.next().and_then(|v| v.parse().ok());
+                if version != Some(REPLAY_VERSION) {
+                    return Err(ReplayError::Version(version));
+                }
+            }
+            None => return Err(ReplayError::NotAReplay),
+        }
+
+        let mut config = None;
+        let mut seed = None;
+        let mut generated = false;
+        let mut mines = None;
+        let mut result = None;
+        let mut steps = Vec::new();
+
+        for (number, line) in lines {
+            let syntax = |message: &str| ReplayError::Syntax {
+                line: number,
+                message: message.to_string(),
+            };
+            let mut words = line.split_whitespace();
+            let first = words.next().unwrap_or_default();
+            match first {
+                "config" => config = Some(parse_config(words).map_err(|m| syntax(&m))?),
+                "seed" => {
+                    seed = Some(
+                        words
+                            .next()
+                            .and_then(|s| s.parse().ok())
+                            .ok_or_else(|| syntax("expected a number after seed"))?,
+                    )
+                }
+                "board" => {
+                    generated = match words.next() {
+                        Some("generated") => true,
+                        Some("fixed") => false,
+                        _ => return Err(syntax("expected generated or fixed after board")),
+                    }
+                }
+                "mines" => {
+                    mines = Some(
+                        words
+                            .map(|word| {
+                                let (x, y) = parse_pair(word, ',')?;
+                                Some(Coord { x, y })
+                            })
+                            .collect::<Option<Vec<Coord>>>()
+                            .ok_or_else(|| syntax("expected mines as x,y"))?,
+                    );
+                }
+                "result" => result = Some(parse_result(words).map_err(|m| syntax(&m))?),
+                _ => steps.push(Step::parse(line).map_err(|m| syntax(&m))?),
+            }
+        }
+
+        let config = config.ok_or(ReplayError::Missing("config"))?;
+        let mines = mines.ok_or(ReplayError::Missing("mines"))?;
+        let outside = |coord: Coord| coord.x >= config.columns() || coord.y >= config.rows();
+        if mines.len() != config.mines() || mines.iter().copied().any(outside) {
+            return Err(ReplayError::Mines);
+        }
+        let mut mines: Vec<usize> = mines
+            .iter()
+            .map(|coord| coord.x + coord.y * config.columns())
+            .collect();
+        mines.sort_unstable();
+        if mines.windows(2).any(|pair| pair[0] == pair[1]) {
+            return Err(ReplayError::DuplicateMine);
+        }
+        if steps
+            .iter()
+            .filter_map(|step| step.action.coord())
+            .any(outside)
+        {
+            return Err(ReplayError::Outside);
+        }
+
+        Ok(Replay {
+            config,
+            seed: seed.ok_or(ReplayError::Missing("seed"))?,
+            generated,
+            mines,
+            result,
+            steps,
+        })
+    }
+}
+
+impl fmt::Display for Replay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let columns = self.config.columns();
+        writeln!(f, "{} {}", MAGIC, REPLAY_VERSION)?;
+        writeln!(
+            f,
+            "config rows={} columns={} mines={} first-click={} protect-flags={}",
+            self.config.rows(),
+            columns,
+            self.config.mines(),
+            self.config.first_click(),
+            self.config.protect_flags()
+        )?;
+        writeln!(f, "seed {}", self.seed)?;
+        writeln!(
+            f,
+            "board {}",
+            if self.generated { "generated" } else { "fixed" }
+        )?;
+        write!(f, "mines")?;
+        for pos in self.mines.iter() {
+            write!(f, " {},{}", pos % columns, pos / columns)?;
+        }
+        writeln!(f)?;
+        if let Some(result) = &self.result {
+            writeln!(
+                f,
+                "result {} time={} 3bv={} undos={} 3bv/s={:.2}",
+                if result.won { "won" } else { "lost" },
+                result.time.as_millis(),
+                result.bbbv,
+                result.undos,
+                result.bbbv_per_second()
+            )?;
+        }
+
+        for step in self.steps.iter() {
+            writeln!(f, "{}", step)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Speeds a replay can be played at, relative to the recorded time
+pub const SPEEDS: [f64; 6] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0];
+
+/**
+ * Plays a replay back on a field, following the recorded time or step by step
+ */
+pub struct Playback {
+    replay: Replay,
+    /// number of steps applied to the field
+    position: usize,
+    /// time into the replay
+    pub clock: Duration,
+    /// index in `SPEEDS`
+    speed: usize,
+    pub paused: bool,
+    /// cell targeted by the last applied action
+    pub target: Option<Coord>,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Playback {
+        Playback {
+            replay,
+            position: 0,
+            clock: Duration::from_secs(0),
+            speed: 2,
+            paused: false,
+            target: None,
+        }
+    }
+
+    /**
+     * Board the replay starts from, its mines are only placed on the first reveal if they were
+     * generated
+     */
+    pub fn field(&self) -> Field {
+        if self.replay.generated {
+            Field::with_seed(&self.replay.config, self.replay.seed)
+        } else {
+            Field::with_mines(&self.replay.config, self.replay.seed, &self.replay.mines)
+        }
+    }
+
+    pub fn speed(&self) -> f64 {
+        SPEEDS[self.speed]
+    }
+
+    pub fn faster(&mut self) {
+        self.speed = (self.speed + 1).min(SPEEDS.len() - 1);
+    }
+
+    pub fn slower(&mut self) {
+        self.speed = self.speed.saturating_sub(1);
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.position == self.replay.steps.len()
+    }
+
+    /**
+     * Advance the clock by `delta` of real time and apply the actions it reached
+     */
+    pub fn tick(&mut self, delta: Duration, field: &mut Field, cursor: &mut Coord) {
+        if self.paused || self.is_finished() {
+            return;
+        }
+
+        self.clock += delta.mul_f64(self.speed());
+        while let Some(step) = self.replay.steps.get(self.position) {
+            if step.time > self.clock {
+                break;
+            }
+            self.apply_next(field, cursor);
+        }
+    }
+
+    /**
+     * Apply the next action, moving the clock to when it happened
+     */
+    pub fn step_forward(&mut self, field: &mut Field, cursor: &mut Coord) {
+        if let Some(step) = self.replay.steps.get(self.position) {
+            self.clock = step.time;
+            self.apply_next(field, cursor);
+        }
+    }
+
+    fn apply_next(&mut self, field: &mut Field, cursor: &mut Coord) {
+        if let Some(step) = self.replay.steps.get(self.position).copied() {
+            self.position += 1;
+            self.target = apply(step.action, field)
+                .map(|pos| field.coord(pos))
+                .or_else(|| step.action.coord());
+            if let Some(target) = self.target {
+                *cursor = target;
+            }
+        }
+    }
+
+    /**
+     * Undo the last applied action by playing the replay again up to the one before it
+     */
+    pub fn step_back(&mut self, field: &mut Field, cursor: &mut Coord) {
+        let position = self.position.saturating_sub(1);
+        self.rewind(field, cursor);
+        while self.position < position {
+            self.step_forward(field, cursor);
+        }
+    }
+
+    /**
+     * Go back to the start of the replay
+     */
+    pub fn rewind(&mut self, field: &mut Field, cursor: &mut Coord) {
+        *field = self.field();
+        *cursor = Coord { x: 0, y: 0 };
+        self.position = 0;
+        self.clock = Duration::from_secs(0);
+        self.target = None;
+    }
+}
+
+/**
+ * Play an action on the field. Undo and redo give the cell they changed.
+ */
+fn apply(action: Action, field: &mut Field) -> Option<usize> {
+    let pos = match action {
+        Action::Undo => return field.undo(),
+        Action::Redo => return field.redo(),
+        // parsed replays only have actions inside of the board
+        _ => action.coord().and_then(|coord| field.index(coord))?,
+    };
+
+    match action {
+        Action::Reveal(_) => {
+            field.reveal_cell(pos);
+        }
+        Action::Chord(_) => {
+            field.chord(pos);
+        }
+        // marks are recorded by their result, with or without question marks
+        Action::Flag(_) => {
+            if field.view(pos, false) != Some(CellView::Flagged) {
+                field.toggle_flag(pos);
+            }
+        }
+        Action::Question(_) => {
+            if field.view(pos, false) == Some(CellView::Flagged) {
+                field.cycle_mark(pos);
+            }
+        }
+        Action::Unflag(_) => match field.view(pos, false) {
+            Some(CellView::Flagged) => field.toggle_flag(pos),
+            Some(CellView::Questioned) => field.cycle_mark(pos),
+            _ => {}
+        },
+        Action::Cursor(_) | Action::Undo | Action::Redo => {}
+    }
+
+    None
+}
+
+fn parse_pair(text: &str, separator: char) -> Option<(usize, usize)> {
+    let mut parts = text.splitn(2, separator);
+    let x = parts.next()?.parse().ok()?;
+    let y = parts.next()?.parse().ok()?;
+    Some((x, y))
+}
+
+fn parse_result<'a>(mut words: impl Iterator<Item = &'a str>) -> Result<GameResult, String> {
+    let won = match words.next() {
+        Some("won") => true,
+        Some("lost") => false,
+        _ => return Err("expected won or lost after result".into()),
+    };
+    let mut time = None;
+    let mut bbbv = None;
+    let mut undos = 0;
+
+    for word in words {
+        let mut parts = word.splitn(2, '=');
+        let key = parts.next().unwrap_or_default();
+        let value = parts
+            .next()
+            .ok_or_else(|| format!("expected key=value, got '{}'", word))?;
+        let invalid = || format!("invalid value for {}: '{}'", key, value);
+        match key {
+            "time" => time = Some(value.parse().map_err(|_| invalid())?),
+            "3bv" => bbbv = Some(value.parse().map_err(|_| invalid())?),
+            "undos" => undos = value.parse().map_err(|_| invalid())?,
+            "3bv/s" => {}
+            _ => return Err(format!("unknown result key '{}'", key)),
+        }
+    }
+
+    match (time, bbbv) {
+        (Some(time), Some(bbbv)) => Ok(GameResult {
+            won,
+            time: Duration::from_millis(time),
+            bbbv,
+            undos,
+        }),
+        _ => Err("the result needs time and 3bv".into()),
+    }
+}
+
+fn parse_config<'a>(words: impl Iterator<Item = &'a str>) -> Result<GameConfig, String> {
+    let mut rows = None;
+    let mut columns = None;
+    let mut mines = None;
+    let mut first_click = None;
+    let mut protect_flags = None;
+
+    for word in words {
+        let mut parts = word.splitn(2, '=');
+        let key = parts.next().unwrap_or_default();
+        let value = parts
+            .next()
+            .ok_or_else(|| format!("expected key=value, got '{}'", word))?;
+        let invalid = || format!("invalid value for {}: '{}'", key, value);
+        match key {
+            "rows" => rows = Some(value.parse().map_err(|_| invalid())?),
+            "columns" => columns = Some(value.parse().map_err(|_| invalid())?),
+            "mines" => mines = Some(value.parse().map_err(|_| invalid())?),
+            "first-click" => first_click = Some(value.parse::<FirstClick>()?),
+            "protect-flags" => protect_flags = Some(value.parse().map_err(|_| invalid())?),
+            _ => return Err(format!("unknown config key '{}'", key)),
+        }
+    }
+
+    match (rows, columns, mines, first_click) {
+        (Some(rows), Some(columns), Some(mines), Some(first_click)) => {
+            GameConfig::new(rows, columns, mines, first_click)
+                .map(|config| config.with_flag_protection(protect_flags.unwrap_or(true)))
+                .map_err(|err: ConfigError| err.to_string())
+        }
+        _ => Err("the config needs rows, columns, mines and first-click".into()),
+    }
+}
+
+fn parse_action<'a>(mut words: impl Iterator<Item = &'a str>) -> Result<Action, String> {
+    let name = words.next().ok_or("expected an action after the time")?;
+    let mut coord = || -> Result<Coord, String> {
+        let x = words.next().and_then(|x| x.parse().ok());
+        let y = words.next().and_then(|y| y.parse().ok());
+        match (x, y) {
+            (Some(x), Some(y)) => Ok(Coord { x, y }),
+            _ => Err(format!("expected the x and y of the cell after {}", name)),
+        }
+    };
+
+    Ok(match name {
+        "reveal" => Action::Reveal(coord()?),
+        "flag" => Action::Flag(coord()?),
+        "unflag" => Action::Unflag(coord()?),
+        "question" => Action::Question(coord()?),
+        "chord" => Action::Chord(coord()?),
+        "cursor" => Action::Cursor(coord()?),
+        "undo" => Action::Undo,
+        "redo" => Action::Redo,
+        _ => return Err(format!("unknown action '{}'", name)),
+    })
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    /// The file doesn't start with `minesweeper-replay`
+    NotAReplay,
+    /// The replay was written with another version of the format
+    Version(Option<u32>),
+    Syntax {
+        line: usize,
+        message: String,
+    },
+    /// One of the config, seed or mines lines is missing
+    Missing(&'static str),
+    /// The mines don't match the config or are outside of the board
+    Mines,
+    DuplicateMine,
+    /// An action targets a cell outside of the board
+    Outside,
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io(err) => write!(f, "could not access the replay: {}", err),
+            ReplayError::NotAReplay => write!(f, "not a replay file"),
+            ReplayError::Version(Some(version)) => write!(
+                f,
+                "the replay uses version {} of the format, expected {}",
+                version, REPLAY_VERSION
+            ),
+            ReplayError::Version(None) => write!(f, "the replay has no version"),
+            ReplayError::Syntax { line, message } => {
+                write!(f, "invalid replay on line {}: {}", line, message)
+            }
+            ReplayError::Missing(entry) => write!(f, "the replay has no {} line", entry),
+            ReplayError::Mines => write!(f, "the mines of the replay don't match its config"),
+            ReplayError::DuplicateMine => write!(f, "the replay has the same mine twice"),
+            ReplayError::Outside => write!(f, "the replay has actions outside of the board"),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replay() -> Replay {
+        let config = GameConfig::new(3, 4, 2, FirstClick::SafeCell)
+            .unwrap()
+            .with_flag_protection(false);
+        let mut field = Field::with_seed(&config, 7);
+        field.reveal_cell(0);
+        let step = |ms, action| Step {
+            time: Duration::from_millis(ms),
+            action,
+        };
+
+        Replay::new(
+            &field,
+            vec![
+                step(0, Action::Reveal(Coord { x: 0, y: 0 })),
+                step(400, Action::Cursor(Coord { x: 3, y: 2 })),
+                step(1200, Action::Flag(Coord { x: 3, y: 2 })),
+                step(1300, Action::Question(Coord { x: 3, y: 2 })),
+                step(1400, Action::Unflag(Coord { x: 3, y: 2 })),
+                step(2000, Action::Undo),
+                step(2100, Action::Redo),
+                step(2500, Action::Chord(Coord { x: 1, y: 1 })),
+            ],
+        )
+    }
+
+    #[test]
+    fn write_and_parse() {
+        let replay = replay();
+        let text = replay.to_string();
+        let mut lines = text.lines();
+        assert_eq!(lines.next(), Some("minesweeper-replay 1"));
+        assert_eq!(
+            lines.next(),
+            Some("config rows=3 columns=4 mines=2 first-click=cell protect-flags=false")
+        );
+        assert_eq!(lines.next(), Some("seed 7"));
+        assert_eq!(lines.next(), Some("board generated"));
+        assert!(lines.next().unwrap().starts_with("mines "));
+        assert_eq!(lines.next(), Some("0 reveal 0 0"));
+        assert_eq!(lines.last(), Some("2500 chord 1 1"));
+
+        let parsed = Replay::parse(&text).unwrap();
+        assert_eq!(parsed.seed, 7);
+        assert!(parsed.generated);
+        assert_eq!(parsed.mines, replay.mines);
+        assert_eq!(parsed.steps, replay.steps);
+        assert_eq!(parsed.config.first_click(), FirstClick::SafeCell);
+        assert!(!parsed.config.protect_flags());
+        assert_eq!(parsed.to_string(), text);
+    }
+
+    #[test]
+    fn results() {
+        let mut replay = replay();
+        replay.result = Some(GameResult {
+            won: true,
+            time: Duration::from_millis(2500),
+            bbbv: 3,
+            undos: 2,
+        });
+        let text = replay.to_string();
+        assert_eq!(
+            text.lines().nth(5),
+            Some("result won time=2500 3bv=3 undos=2 3bv/s=1.20")
+        );
+        assert_eq!(Replay::parse(&text).unwrap().result, replay.result);
+
+        let header = "minesweeper-replay 1\nconfig rows=2 columns=2 mines=1 first-click=area\nseed 3\nmines 1,1\n";
+        assert!(matches!(
+            Replay::parse(&format!("{}result tied time=10 3bv=1\n", header)),
+            Err(ReplayError::Syntax { line: 5, .. })
+        ));
+        assert!(matches!(
+            Replay::parse(&format!("{}result lost 3bv=1\n", header)),
+            Err(ReplayError::Syntax { line: 5, .. })
+        ));
+        assert!(matches!(
+            Replay::parse(&format!("{}result lost time=10 3bv=1 undos=-1\n", header)),
+            Err(ReplayError::Syntax { line: 5, .. })
+        ));
+        assert_eq!(
+            Replay::parse(&format!("{}result lost time=10 3bv=1\n", header))
+                .unwrap()
+                .result
+                .map(|result| result.undos),
+            Some(0)
+        );
+        assert_eq!(
+            Replay::parse(&format!("{}result lost time=0 3bv=1\n", header))
+                .unwrap()
+                .result
+                .map(|result| result.bbbv_per_second()),
+            Some(0.0)
+        );
+
+        // the time is written in milliseconds, below that the game took no time
+        replay.result = Some(GameResult {
+            won: false,
+            time: Duration::from_micros(15),
+            bbbv: 1,
+            undos: 0,
+        });
+        let text = replay.to_string();
+        assert_eq!(
+            text.lines().nth(5),
+            Some("result lost time=0 3bv=1 undos=0 3bv/s=0.00")
+        );
+        let parsed = Replay::parse(&text).unwrap().result.unwrap();
+        assert_eq!(parsed.bbbv_per_second(), 0.0);
+        replay.result = Some(GameResult {
+            won: true,
+            time: Duration::from_micros(1_500_900),
+            bbbv: 3,
+            undos: 0,
+        });
+        assert_eq!(replay.result.unwrap().bbbv_per_second(), 2.0);
+    }
+
+    #[test]
+    fn parse_ignores_comments_and_blank_lines() {
+        let replay = Replay::parse(
+            "\
+# a game from last week
+minesweeper-replay 1
+
+config rows=2 columns=2 mines=1 first-click=unsafe
+seed 3
+mines 1,1
+# the end
+10 reveal 0 0
+",
+        )
+        .unwrap();
+
+        assert_eq!(replay.mines, vec![3]);
+        assert!(!replay.generated);
+        assert!(replay.config.protect_flags());
+        assert_eq!(
+            replay.steps,
+            vec![Step {
+                time: Duration::from_millis(10),
+                action: Action::Reveal(Coord { x: 0, y: 0 }),
+            }]
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        let header = "minesweeper-replay 1\nconfig rows=2 columns=2 mines=1 first-click=area\n";
+
+        assert!(matches!(
+            Replay::parse("seed 3"),
+            Err(ReplayError::NotAReplay)
+        ));
+        assert!(matches!(
+            Replay::parse("minesweeper-replay 2"),
+            Err(ReplayError::Version(Some(2)))
+        ));
+        assert!(matches!(
+            Replay::parse(&format!("{}seed 3\n", header)),
+            Err(ReplayError::Missing("mines"))
+        ));
+        assert!(matches!(
+            Replay::parse(&format!("{}seed 3\nmines 1,1 0,0\n", header)),
+            Err(ReplayError::Mines)
+        ));
+        // 2,0 would be 0,1 on a board with 2 columns
+        assert!(matches!(
+            Replay::parse(&format!("{}seed 3\nmines 2,0\n", header)),
+            Err(ReplayError::Mines)
+        ));
+        assert!(matches!(
+            Replay::parse(
+                "minesweeper-replay 1\nconfig rows=2 columns=2 mines=2 first-click=area\nseed 3\nmines 1,1 1,1\n"
+            ),
+            Err(ReplayError::DuplicateMine)
+        ));
+        assert!(matches!(
+            Replay::parse(&format!("{}seed 3\nmines 1,1\n10 reveal 2 0\n", header)),
+            Err(ReplayError::Outside)
+        ));
+        assert!(matches!(
+            Replay::parse(&format!("{}seed 3\nmines 1,1\n10 jump 0 0\n", header)),
+            Err(ReplayError::Syntax { line: 5, .. })
+        ));
+        assert!(matches!(
+            Replay::parse(&format!("{}seed 3\nboard random\nmines 1,1\n", header)),
+            Err(ReplayError::Syntax { line: 4, .. })
+        ));
+        assert!(matches!(
+            Replay::parse("minesweeper-replay 1\nconfig rows=2 columns=0 mines=1 first-click=area"),
+            Err(ReplayError::Syntax { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn playback() {
+        let field = Field::from(vec!["xoo", "ooo", "oox"]);
+        let step = |ms, action| Step {
+            time: Duration::from_millis(ms),
+            action,
+        };
+        let replay = Replay::new(
+            &field,
+            vec![
+                step(100, Action::Reveal(Coord { x: 2, y: 0 })),
+                step(200, Action::Flag(Coord { x: 0, y: 0 })),
+                step(300, Action::Question(Coord { x: 0, y: 0 })),
+                step(400, Action::Unflag(Coord { x: 0, y: 0 })),
+                step(500, Action::Undo),
+                step(600, Action::Cursor(Coord { x: 1, y: 2 })),
+                step(2000, Action::Reveal(Coord { x: 0, y: 2 })),
+            ],
+        );
+        let mut playback = Playback::new(replay);
+        let mut field = playback.field();
+        let mut cursor = Coord { x: 0, y: 0 };
+
+        playback.tick(Duration::from_millis(250), &mut field, &mut cursor);
+        assert_eq!(field.view(2, false), Some(CellView::Revealed(0)));
+        assert_eq!(field.view(0, false), Some(CellView::Flagged));
+        assert_eq!(playback.target, Some(Coord { x: 0, y: 0 }));
+
+        playback.faster();
+        assert_eq!(playback.speed(), 2.0);
+        playback.tick(Duration::from_millis(200), &mut field, &mut cursor);
+        // the unflag was undone
+        assert_eq!(field.view(0, false), Some(CellView::Questioned));
+        assert_eq!(cursor, Coord { x: 1, y: 2 });
+        assert!(!playback.is_finished());
+
+        playback.step_forward(&mut field, &mut cursor);
+        assert!(playback.is_finished());
+        assert_eq!(field.state(), GameState::Won);
+        assert_eq!(playback.clock, Duration::from_secs(2));
+
+        playback.step_back(&mut field, &mut cursor);
+        playback.step_back(&mut field, &mut cursor);
+        assert_eq!(field.view(0, false), Some(CellView::Questioned));
+        assert_eq!(field.state(), GameState::Playing);
+        assert_eq!(playback.clock, Duration::from_millis(500));
+        assert_eq!(playback.target, Some(Coord { x: 0, y: 0 }));
+
+        playback.rewind(&mut field, &mut cursor);
+        assert_eq!(field.state(), GameState::NotStarted);
+        assert_eq!(field.view(0, false), Some(CellView::Hidden));
+    }
+
+    #[test]
+    fn playback_places_generated_mines_again() {
+        let config = GameConfig::new(9, 9, 10, FirstClick::SafeArea).unwrap();
+        let mut first = Field::with_seed(&config, 0);
+        first.reveal_cell(0);
+        let mut field = Field::with_seed(&config, 0);
+        field.reveal_cell(0);
+        field.undo();
+        field.reveal_cell(80);
+        let step = |ms, action| Step {
+            time: Duration::from_millis(ms),
+            action,
+        };
+        let replay = Replay::new(
+            &field,
+            vec![
+                step(100, Action::Reveal(Coord { x: 0, y: 0 })),
+                step(200, Action::Undo),
+                step(300, Action::Reveal(Coord { x: 8, y: 8 })),
+            ],
+        );
+        assert!(replay.generated);
+        let replay = Replay::parse(&replay.to_string()).unwrap();
+        assert!(replay.generated);
+
+        let mut playback = Playback::new(replay);
+        let mut played = playback.field();
+        let mut cursor = Coord { x: 0, y: 0 };
+        // the first reveal sees the mines it placed, not the final ones
+        playback.step_forward(&mut played, &mut cursor);
+        assert_eq!(played.as_text_ascii(true), first.as_text_ascii(true));
+        assert_ne!(first.mine_positions(), field.mine_positions());
+        playback.step_forward(&mut played, &mut cursor);
+        assert_eq!(played.state(), GameState::NotStarted);
+        playback.step_forward(&mut played, &mut cursor);
+        assert_eq!(played.as_text_ascii(true), field.as_text_ascii(true));
+        assert_eq!(played.as_text_ascii(false), field.as_text_ascii(false));
+
+        // fixed boards keep their mines from the start
+        let fixed = Field::with_mines(&config, 0, &field.mine_positions());
+        let replay = Replay::new(&fixed, Vec::new());
+        assert!(!replay.generated);
+        assert_eq!(
+            Playback::new(replay).field().mine_positions(),
+            field.mine_positions()
+        );
+    }
+}
@@ -0,0 +1,291 @@
+use crate::game::{Field, RestoreError, SavedField};
+use crate::replay::{Recording, Step};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Version of the save file format, bumped when older files can't be read anymore
+pub const SAVE_VERSION: u32 = 2;
+
+/// A game in progress, written on quit and read back with `--resume`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavedGame {
+    version: u32,
+    /// time spent on the game so far, in milliseconds
+    elapsed: u64,
+    field: SavedField,
+    /// actions of the game if it's recorded, puzzles aren't
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    recording: Option<SavedRecording>,
+}
+
+/// See `Recording`
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedRecording {
+    /// time since the recording started, in milliseconds
+    time: u64,
+    /// one action per line, as written in replays
+    steps: Vec<String>,
+}
+
+impl SavedGame {
+    pub fn new(field: &Field, elapsed: Duration, recording: Option<&Recording>) -> SavedGame {
+        SavedGame {
+            version: SAVE_VERSION,
+            elapsed: elapsed.as_millis() as u64,
+            field: field.save(),
+            recording: recording.map(|recording| SavedRecording {
+                time: recording.time.as_millis() as u64,
+                steps: recording.steps.iter().map(Step::to_string).collect(),
+            }),
+        }
+    }
+
+    pub fn from_toml(text: &str) -> Result<SavedGame, SaveError> {
+        let saved: SavedGame = toml::from_str(text).map_err(SaveError::Parse)?;
+        if saved.version != SAVE_VERSION {
+            return Err(SaveError::Version(saved.version));
+        }
+
+        Ok(saved)
+    }
+
+    pub fn to_toml(&self) -> Result<String, SaveError> {
+        toml::to_string(self).map_err(SaveError::Serialize)
+    }
+
+    pub fn read(path: &Path) -> Result<SavedGame, SaveError> {
+        let text = fs::read_to_string(path).map_err(SaveError::Io)?;
+        SavedGame::from_toml(&text)
+    }
+
+    /**
+     * Write the game to `path`, creating the missing directories
+     */
+    pub fn write(&self, path: &Path) -> Result<(), SaveError> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(SaveError::Io)?;
+        }
+        fs::write(path, self.to_toml()?).map_err(SaveError::Io)
+    }
+
+    /**
+     * The field, the time spent on it and its recording, ready to be played
+     */
+    pub fn into_game(self) -> Result<(Field, Duration, Option<Recording>), SaveError> {
+        let field = Field::restore(self.field).map_err(SaveError::Invalid)?;
+        let recording = match self.recording {
+            Some(saved) => {
+                let steps = saved
+                    .steps
+                    .iter()
+                    .map(|line| Step::parse(line))
+                    .collect::<Result<Vec<Step>, String>>()
+                    .map_err(SaveError::Steps)?;
+                if steps
+                    .iter()
+                    .filter_map(|step| step.action.coord())
+                    .any(|coord| field.index(coord).is_none())
+                {
+                    return Err(SaveError::Steps(
+                        "an action targets a cell outside of the board".into(),
+                    ));
+                }
+                Some(Recording {
+                    time: Duration::from_millis(saved.time),
+                    steps,
+                })
+            }
+            None => None,
+        };
+        Ok((field, Duration::from_millis(self.elapsed), recording))
+    }
+}
+
+/**
+ * Where the game keeps its files: `$XDG_DATA_HOME/minesweeper`, falling back to
+ * `~/.local/share` when `XDG_DATA_HOME` isn't set
+ */
+pub fn data_dir() -> Option<PathBuf> {
+    let data_dir = match env::var_os("XDG_DATA_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".local/share"),
+    };
+
+    Some(data_dir.join("minesweeper"))
+}
+
+/**
+ * Where the game in progress is saved on quit
+ */
+pub fn save_path() -> Option<PathBuf> {
+    Some(data_dir()?.join("save.toml"))
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+    Serialize(toml::ser::Error),
+    /// The file was written by another version of the game
+    Version(u32),
+    Invalid(RestoreError),
+    /// One of the recorded actions can't be read
+    Steps(String),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Io(err) => write!(f, "could not access the saved game: {}", err),
+            SaveError::Parse(err) => write!(f, "invalid saved game: {}", err),
+            SaveError::Serialize(err) => write!(f, "could not save the game: {}", err),
+            SaveError::Version(version) => write!(
+                f,
+                "the saved game uses version {} of the format, expected {}",
+                version, SAVE_VERSION
+            ),
+            SaveError::Invalid(err) => write!(f, "invalid saved game: {}", err),
+            SaveError::Steps(message) => write!(f, "invalid recorded action: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{CellView, Coord, FirstClick, GameConfig, GameState};
+    use crate::replay::Action;
+
+    #[test]
+    fn save_and_resume() {
+        let config = GameConfig::new(16, 30, 99, FirstClick::SafeArea).unwrap();
+        let mut field = Field::with_seed(&config, 42);
+        field.reveal_cell(40);
+        field.cycle_mark(0);
+        field.cycle_mark(0);
+        field.toggle_flag(1);
+        field.undo();
+
+        let recording = Recording {
+            time: Duration::from_millis(15_000),
+            steps: vec![
+                Step {
+                    time: Duration::from_millis(1200),
+                    action: Action::Reveal(Coord { x: 10, y: 1 }),
+                },
+                Step {
+                    time: Duration::from_millis(2500),
+                    action: Action::Undo,
+                },
+            ],
+        };
+        let text = SavedGame::new(&field, Duration::from_millis(12_345), Some(&recording))
+            .to_toml()
+            .unwrap();
+        let (mut resumed, elapsed, resumed_recording) =
+            SavedGame::from_toml(&text).unwrap().into_game().unwrap();
+
+        assert_eq!(elapsed, Duration::from_millis(12_345));
+        assert_eq!(resumed_recording, Some(recording));
+        assert_eq!(resumed.seed(), 42);
+        assert_eq!(resumed.state(), GameState::Playing);
+        assert_eq!(resumed.undo_count(), 1);
+        assert_eq!(resumed.as_text_ascii(true), field.as_text_ascii(true));
+        assert_eq!(resumed.as_text_ascii(false), field.as_text_ascii(false));
+        assert_eq!(resumed.view(0, false), Some(CellView::Questioned));
+
+        // the moves come along
+        assert_eq!(resumed.redo(), Some(1));
+        assert_eq!(resumed.view(1, false), Some(CellView::Flagged));
+        resumed.undo();
+        resumed.undo();
+        resumed.undo();
+        resumed.undo();
+        assert_eq!(resumed.state(), GameState::NotStarted);
+        resumed.redo();
+        assert_eq!(resumed.as_text_ascii(true), field.as_text_ascii(true));
+    }
+
+    #[test]
+    fn invalid_saves() {
+        let field = Field::with_seed(&crate::game::CONFIG_BEGINNER, 1);
+        let text = SavedGame::new(&field, Duration::from_secs(0), None)
+            .to_toml()
+            .unwrap();
+
+        assert!(matches!(
+            SavedGame::from_toml(&text.replace("version = 2", "version = 1")),
+            Err(SaveError::Version(1))
+        ));
+        assert!(matches!(
+            SavedGame::from_toml("version = 2"),
+            Err(SaveError::Parse(_))
+        ));
+        assert!(matches!(
+            SavedGame::from_toml(&text.replace(".........", "........"))
+                .unwrap()
+                .into_game(),
+            Err(SaveError::Invalid(RestoreError::Cells))
+        ));
+        // the config is checked while parsing
+        assert!(matches!(
+            SavedGame::from_toml(&text.replace("mines = 10", "mines = 100")),
+            Err(SaveError::Parse(_))
+        ));
+
+        // the mines are in the cells once placed, and the numbers have to match them
+        let mut field = field;
+        field.reveal_cell(40);
+        let text = SavedGame::new(&field, Duration::from_secs(0), None)
+            .to_toml()
+            .unwrap();
+        assert!(text.contains("\".x....xxx\", \"...x.....\", \"...x.....\", \"..2111...\""));
+        assert!(matches!(
+            SavedGame::from_toml(&text.replacen("xxx", "xx.", 1))
+                .unwrap()
+                .into_game(),
+            Err(SaveError::Invalid(RestoreError::Mines))
+        ));
+        assert!(matches!(
+            SavedGame::from_toml(&text.replacen("2111", "3111", 1))
+                .unwrap()
+                .into_game(),
+            Err(SaveError::Invalid(RestoreError::Cells))
+        ));
+        assert!(SavedGame::from_toml(&text.replacen("..2111", "f.2111", 1))
+            .unwrap()
+            .into_game()
+            .is_ok());
+
+        // and so do the recorded actions
+        let recording = |step: &str| {
+            format!(
+                "{}\n[recording]\ntime = 100\nsteps = [\"{}\"]\n",
+                text, step
+            )
+        };
+        assert!(SavedGame::from_toml(&recording("10 reveal 8 8"))
+            .unwrap()
+            .into_game()
+            .is_ok());
+        assert!(matches!(
+            SavedGame::from_toml(&recording("10 reveal 9 8"))
+                .unwrap()
+                .into_game(),
+            Err(SaveError::Steps(_))
+        ));
+        assert!(matches!(
+            SavedGame::from_toml(&recording("10 jump"))
+                .unwrap()
+                .into_game(),
+            Err(SaveError::Steps(_))
+        ));
+    }
+}
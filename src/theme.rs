@@ -0,0 +1,481 @@
+use serde::Deserialize;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use tui::style::{Color, Modifier, Style};
+use unicode_width::UnicodeWidthStr;
+
+/// Number of terminal columns taken by a cell of the minefield
+pub const CELL_WIDTH: usize = 2;
+
+/// Names of the themes that can be used without a theme file
+pub const BUILTIN_THEMES: [&str; 4] = ["classic", "ascii", "high-contrast", "colour-blind"];
+
+/// Symbol of a cell and the style it's drawn with
+#[derive(Debug, Clone, PartialEq)]
+pub struct Glyph {
+    /// Always `CELL_WIDTH` columns wide, wider symbols are rejected when reading theme files
+    pub symbol: String,
+    pub style: Style,
+}
+
+impl Glyph {
+    pub fn new(symbol: &str, style: Style) -> Glyph {
+        Glyph {
+            symbol: pad_symbol(symbol),
+            style,
+        }
+    }
+}
+
+/**
+ * Pad a symbol with spaces so every cell takes the same number of columns
+ */
+fn pad_symbol(symbol: &str) -> String {
+    let width = UnicodeWidthStr::width(symbol);
+    format!("{}{}", symbol, " ".repeat(CELL_WIDTH.saturating_sub(width)))
+}
+
+/// Glyphs and colours used to draw the minefield
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub hidden: Glyph,
+    pub flag: Glyph,
+    pub question: Glyph,
+    pub mine: Glyph,
+    /// Mine hit by the player, shown when the game is lost
+    pub exploded: Glyph,
+    /// Flag on a cell without a mine, shown when the game is lost
+    pub wrong_flag: Glyph,
+    /// Revealed cell without mines around
+    pub empty: Glyph,
+    /// Revealed cells with 1 to 8 mines around
+    pub numbers: [Glyph; 8],
+    /// Style of the whole minefield, cells are drawn on top of it
+    pub board: Style,
+    pub border: Style,
+}
+
+impl Theme {
+    pub fn classic() -> Theme {
+        Theme {
+            hidden: Glyph::new("🔲", Style::default()),
+            flag: Glyph::new("🚩", Style::default()),
+            question: Glyph::new("❓", Style::default()),
+            mine: Glyph::new(
+                "💣",
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            ),
+            exploded: Glyph::new("💥", Style::default().bg(Color::Red)),
+            wrong_flag: Glyph::new("❌", Style::default()),
+            empty: Glyph::new("", Style::default()),
+            numbers: [
+                Glyph::new("①", Style::default().fg(Color::LightBlue)),
+                Glyph::new("②", Style::default().fg(Color::LightGreen)),
+                Glyph::new("③", Style::default().fg(Color::LightYellow)),
+                Glyph::new("④", Style::default().fg(Color::LightMagenta)),
+                Glyph::new("⑤", Style::default().fg(Color::LightRed)),
+                Glyph::new(
+                    "⑥",
+                    Style::default()
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                Glyph::new(
+                    "⑦",
+                    Style::default()
+                        .fg(Color::Blue)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                Glyph::new(
+                    "⑧",
+                    Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                ),
+            ],
+            board: Style::default(),
+            border: Style::default(),
+        }
+    }
+
+    /**
+     * Same colours as the classic theme, with symbols that render the same in every terminal
+     */
+    pub fn ascii() -> Theme {
+        let classic = Theme::classic();
+        let mut numbers = classic.numbers.clone();
+        for (i, number) in numbers.iter_mut().enumerate() {
+            number.symbol = pad_symbol(&(i + 1).to_string());
+        }
+
+        Theme {
+            hidden: Glyph::new("#", Style::default()),
+            flag: Glyph::new("f", Style::default().fg(Color::LightRed)),
+            question: Glyph::new("?", Style::default().fg(Color::LightCyan)),
+            mine: Glyph::new("x", classic.mine.style),
+            exploded: Glyph::new(
+                "X",
+                Style::default()
+                    .fg(Color::White)
+                    .bg(Color::Red)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            wrong_flag: Glyph::new("!", Style::default().fg(Color::LightRed)),
+            empty: Glyph::new("-", Style::default().fg(Color::DarkGray)),
+            numbers,
+            ..classic
+        }
+    }
+
+    pub fn high_contrast() -> Theme {
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        let number = |n: &str, color| Glyph::new(n, bold.fg(color));
+
+        Theme {
+            hidden: Glyph::new("██", Style::default().fg(Color::Gray)),
+            flag: Glyph::new("F", bold.fg(Color::Black).bg(Color::Yellow)),
+            question: Glyph::new("?", bold.fg(Color::Black).bg(Color::Cyan)),
+            mine: Glyph::new("*", bold.fg(Color::White).bg(Color::Red)),
+            exploded: Glyph::new("X", bold.fg(Color::Black).bg(Color::LightRed)),
+            wrong_flag: Glyph::new("!", bold.fg(Color::Black).bg(Color::Magenta)),
+            empty: Glyph::new("", Style::default()),
+            numbers: [
+                number("1", Color::LightCyan),
+                number("2", Color::LightGreen),
+                number("3", Color::LightYellow),
+                number("4", Color::LightMagenta),
+                number("5", Color::LightRed),
+                number("6", Color::White),
+                number("7", Color::White),
+                number("8", Color::White),
+            ],
+            board: Style::default().fg(Color::White).bg(Color::Black),
+            border: bold.fg(Color::White),
+        }
+    }
+
+    /**
+     * Numbers use the Okabe-Ito palette, which stays distinguishable with the common forms of
+     * colour blindness
+     */
+    pub fn colour_blind() -> Theme {
+        let number = |n: &str, r, g, b| Glyph::new(n, Style::default().fg(Color::Rgb(r, g, b)));
+
+        Theme {
+            numbers: [
+                number("1", 0, 114, 178),
+                number("2", 0, 158, 115),
+                number("3", 213, 94, 0),
+                number("4", 204, 121, 167),
+                number("5", 230, 159, 0),
+                number("6", 86, 180, 233),
+                number("7", 240, 228, 66),
+                number("8", 255, 255, 255),
+            ],
+            ..Theme::classic()
+        }
+    }
+
+    pub fn builtin(name: &str) -> Option<Theme> {
+        match name {
+            "classic" => Some(Theme::classic()),
+            "ascii" => Some(Theme::ascii()),
+            "high-contrast" => Some(Theme::high_contrast()),
+            "colour-blind" => Some(Theme::colour_blind()),
+            _ => None,
+        }
+    }
+
+    pub fn from_file(path: &Path) -> Result<Theme, ThemeError> {
+        let text = fs::read_to_string(path).map_err(ThemeError::Io)?;
+        Theme::from_toml(&text)
+    }
+
+    /**
+     * Parse a theme file. Every key is optional and falls back to the theme named by `base`
+     * (classic by default).
+     */
+    pub fn from_toml(text: &str) -> Result<Theme, ThemeError> {
+        let file: ThemeFile = toml::from_str(text).map_err(ThemeError::Parse)?;
+        let base = file.base.as_deref().unwrap_or("classic");
+        let mut theme =
+            Theme::builtin(base).ok_or_else(|| ThemeError::UnknownTheme(base.to_string()))?;
+
+        let mut glyphs = [
+            (&file.hidden, &mut theme.hidden),
+            (&file.flag, &mut theme.flag),
+            (&file.question, &mut theme.question),
+            (&file.mine, &mut theme.mine),
+            (&file.exploded, &mut theme.exploded),
+            (&file.wrong_flag, &mut theme.wrong_flag),
+            (&file.empty, &mut theme.empty),
+        ];
+        for (glyph_file, glyph) in glyphs.iter_mut() {
+            if let Some(glyph_file) = glyph_file {
+                glyph_file.apply(glyph)?;
+            }
+        }
+
+        if let Some(numbers) = &file.numbers {
+            if numbers.len() != theme.numbers.len() {
+                return Err(ThemeError::Numbers(numbers.len()));
+            }
+            for (glyph_file, glyph) in numbers.iter().zip(theme.numbers.iter_mut()) {
+                glyph_file.apply(glyph)?;
+            }
+        }
+
+        if let Some(board) = &file.board {
+            theme.board = board.apply(theme.board)?;
+        }
+        if let Some(border) = &file.border {
+            theme.border = border.apply(theme.border)?;
+        }
+
+        Ok(theme)
+    }
+}
+
+#[derive(Debug)]
+pub enum ThemeError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+    /// `base` isn't one of the `BUILTIN_THEMES`
+    UnknownTheme(String),
+    UnknownColor(String),
+    /// `numbers` must have one entry for each number from 1 to 8
+    Numbers(usize),
+    /// A symbol doesn't fit in a cell
+    SymbolWidth(String),
+}
+
+impl fmt::Display for ThemeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ThemeError::Io(err) => write!(f, "could not read the theme file: {}", err),
+            ThemeError::Parse(err) => write!(f, "invalid theme file: {}", err),
+            ThemeError::UnknownTheme(name) => write!(
+                f,
+                "unknown theme '{}', expected one of: {}",
+                name,
+                BUILTIN_THEMES.join(", ")
+            ),
+            ThemeError::UnknownColor(color) => write!(
+                f,
+                "unknown color '{}', expected a color name like 'lightblue' or '#rrggbb'",
+                color
+            ),
+            ThemeError::Numbers(count) => write!(
+                f,
+                "the theme must define the 8 numbers, got {} of them",
+                count
+            ),
+            ThemeError::SymbolWidth(symbol) => write!(
+                f,
+                "the symbol '{}' is wider than a cell, it can take at most {} columns",
+                symbol, CELL_WIDTH
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ThemeError {}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ThemeFile {
+    base: Option<String>,
+    hidden: Option<GlyphFile>,
+    flag: Option<GlyphFile>,
+    question: Option<GlyphFile>,
+    mine: Option<GlyphFile>,
+    exploded: Option<GlyphFile>,
+    wrong_flag: Option<GlyphFile>,
+    empty: Option<GlyphFile>,
+    numbers: Option<Vec<GlyphFile>>,
+    board: Option<StyleFile>,
+    border: Option<StyleFile>,
+}
+
+#[derive(Deserialize)]
+struct GlyphFile {
+    symbol: Option<String>,
+    #[serde(flatten)]
+    style: StyleFile,
+}
+
+impl GlyphFile {
+    fn apply(&self, glyph: &mut Glyph) -> Result<(), ThemeError> {
+        if let Some(symbol) = &self.symbol {
+            if UnicodeWidthStr::width(symbol.as_str()) > CELL_WIDTH {
+                return Err(ThemeError::SymbolWidth(symbol.clone()));
+            }
+            glyph.symbol = pad_symbol(symbol);
+        }
+        glyph.style = self.style.apply(glyph.style)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+struct StyleFile {
+    fg: Option<String>,
+    bg: Option<String>,
+    bold: Option<bool>,
+    italic: Option<bool>,
+    underlined: Option<bool>,
+}
+
+impl StyleFile {
+    fn apply(&self, mut style: Style) -> Result<Style, ThemeError> {
+        if let Some(fg) = &self.fg {
+            style = style.fg(parse_color(fg)?);
+        }
+        if let Some(bg) = &self.bg {
+            style = style.bg(parse_color(bg)?);
+        }
+        let modifiers = [
+            (self.bold, Modifier::BOLD),
+            (self.italic, Modifier::ITALIC),
+            (self.underlined, Modifier::UNDERLINED),
+        ];
+        for (enabled, modifier) in modifiers.iter() {
+            match enabled {
+                Some(true) => style = style.add_modifier(*modifier),
+                Some(false) => style = style.remove_modifier(*modifier),
+                None => {}
+            }
+        }
+
+        Ok(style)
+    }
+}
+
+/**
+ * Parse a color name (`red`, `lightblue`, `dark-gray`...) or an rgb hex code (`#ff8800`)
+ */
+fn parse_color(text: &str) -> Result<Color, ThemeError> {
+    let name: String = text
+        .to_lowercase()
+        .chars()
+        .filter(|&c| c != '-' && c != '_')
+        .collect();
+    let color = match name.as_str() {
+        "reset" => Color::Reset,
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "gray" | "grey" => Color::Gray,
+        "darkgray" | "darkgrey" => Color::DarkGray,
+        "lightred" => Color::LightRed,
+        "lightgreen" => Color::LightGreen,
+        "lightyellow" => Color::LightYellow,
+        "lightblue" => Color::LightBlue,
+        "lightmagenta" => Color::LightMagenta,
+        "lightcyan" => Color::LightCyan,
+        "white" => Color::White,
+        _ => {
+            let hex = name
+                .strip_prefix('#')
+                .filter(|hex| hex.len() == 6)
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| ThemeError::UnknownColor(text.to_string()))?;
+            Color::Rgb((hex >> 16) as u8, (hex >> 8) as u8, hex as u8)
+        }
+    };
+
+    Ok(color)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn symbols_are_padded() {
+        assert_eq!(Glyph::new("x", Style::default()).symbol, "x ");
+        assert_eq!(Glyph::new("💣", Style::default()).symbol, "💣");
+        assert_eq!(Glyph::new("", Style::default()).symbol, "  ");
+        for name in BUILTIN_THEMES.iter() {
+            let theme = Theme::builtin(name).unwrap();
+            for glyph in [
+                &theme.hidden,
+                &theme.flag,
+                &theme.question,
+                &theme.mine,
+                &theme.exploded,
+                &theme.wrong_flag,
+            ]
+            .iter()
+            .chain(theme.numbers.iter().collect::<Vec<_>>().iter())
+            {
+                assert_eq!(UnicodeWidthStr::width(glyph.symbol.as_str()), CELL_WIDTH);
+            }
+        }
+    }
+
+    #[test]
+    fn parse_theme_file() {
+        let theme = Theme::from_toml(
+            r##"
+base = "ascii"
+hidden = { symbol = "o", fg = "dark-gray" }
+flag = { fg = "#ff8800", bold = true }
+border = { fg = "LightBlue" }
+"##,
+        )
+        .unwrap();
+
+        assert_eq!(theme.hidden.symbol, "o ");
+        assert_eq!(theme.hidden.style, Style::default().fg(Color::DarkGray));
+        assert_eq!(theme.flag.symbol, "f ");
+        assert_eq!(
+            theme.flag.style,
+            Style::default()
+                .fg(Color::Rgb(0xff, 0x88, 0x00))
+                .add_modifier(Modifier::BOLD)
+        );
+        assert_eq!(theme.border, Style::default().fg(Color::LightBlue));
+        assert_eq!(theme.mine, Theme::ascii().mine);
+    }
+
+    #[test]
+    fn theme_file_errors() {
+        assert!(matches!(
+            Theme::from_toml(r#"base = "neon""#),
+            Err(ThemeError::UnknownTheme(_))
+        ));
+        assert!(matches!(
+            Theme::from_toml(r#"mine = { fg = "blurple" }"#),
+            Err(ThemeError::UnknownColor(_))
+        ));
+        assert!(matches!(
+            Theme::from_toml(r#"numbers = [{ symbol = "1" }]"#),
+            Err(ThemeError::Numbers(1))
+        ));
+        assert!(matches!(
+            Theme::from_toml(r#"hidden = { symbol = "abc" }"#),
+            Err(ThemeError::SymbolWidth(symbol)) if symbol == "abc"
+        ));
+        assert!(matches!(
+            Theme::from_toml(r#"flag = { symbol = "🚩!" }"#),
+            Err(ThemeError::SymbolWidth(_))
+        ));
+        assert_eq!(
+            Theme::from_toml(r#"hidden = { symbol = "[]" }"#)
+                .unwrap()
+                .hidden
+                .symbol,
+            "[]"
+        );
+        assert!(matches!(
+            Theme::from_toml(r#"hiden = { symbol = "1" }"#),
+            Err(ThemeError::Parse(_))
+        ));
+    }
+}
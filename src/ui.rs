@@ -1,61 +1,65 @@
 use crate::app;
 use crate::game;
+use crate::theme;
 
 use tui::{
     backend::Backend,
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
-    symbols,
     text::{Span, Spans},
-    widgets::canvas::{Canvas, Line, Map, MapResolution, Rectangle},
-    widgets::{
-        Axis, BarChart, Block, Borders, Chart, Dataset, Gauge, LineGauge, List, ListItem,
-        Paragraph, Row, Sparkline, Table, Tabs, Wrap,
-    },
+    widgets::{Block, Borders, Paragraph},
     Frame,
 };
 
+/// Number of terminal columns taken by a cell of the minefield
+const CELL_WIDTH: u16 = theme::CELL_WIDTH as u16;
+/// Height of the config box under the minefield, borders included
+const CONFIG_HEIGHT: u16 = 5;
+/// Biggest size of the minimap shown when the board doesn't fit in the terminal
+const MAP_MAX_WIDTH: u16 = 20;
+const MAP_MAX_HEIGHT: u16 = 4;
+
 pub fn draw<B: Backend>(f: &mut Frame<B>, app: &mut app::App) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([Constraint::Length(1), Constraint::Min(0)].as_ref())
         .split(f.size());
 
-    draw_title(f, chunks[0], &app);
-    draw_screen(f, chunks[1], &app.field);
-
-    let screen = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([Constraint::Length(app.field.config.columns as u16 * 2)].as_ref())
-        .split(f.size());
-
-    // f.render_widget(screen, chunks[1]);
-
-    let block = Block::default()
-        .title(format!("({}, {})", app.click.0, app.click.1))
-        .style(Style::default().fg(Color::White).bg(Color::Black))
-        .borders(Borders::ALL);
-    // f.render_widget(block, screen[0]);
-
-    // let chunks = Layout::default()
-    //   .constraints([Constraint::Length(3), Constraint::Min(0)].as_ref())
-    //   .split(f.size());
-    // let titles = app
-    //   .tabs
-    //   .titles
-    //   .iter()
-    //   .map(|t| Spans::from(Span::styled(*t, Style::default().fg(Color::Green))))
-    //   .collect();
+    draw_title(f, chunks[0], app);
+    draw_screen(f, chunks[1], app);
 }
 
 fn draw_title<B>(f: &mut Frame<B>, area: Rect, app: &app::App)
 where
     B: Backend,
 {
+    let (won, lost, mines, time) = if app.enhanced_graphics {
+        (" - You won! 🎉", " - Game over 💥", "💣", "⏱ ")
+    } else {
+        (" - You won!", " - Game over", "Mines", "Time")
+    };
+    let mut status = match app.field.state() {
+        game::GameState::Won => won,
+        game::GameState::Lost => lost,
+        _ => "",
+    }
+    .to_string();
+    if let Some(playback) = &app.playback {
+        status.push_str(&format!(" - Replay {}x", playback.speed()));
+        if playback.paused {
+            status.push_str(" paused");
+        }
+    }
     let text = Span::styled(
         format!(
-            "Minesweeper {} for ({}x{})",
-            app.last_reveal, app.click.0, app.click.1
+            "{} (seed {}) {} {:03} {} {:03}{}",
+            app.title,
+            app.field.seed(),
+            mines,
+            app.field.remaining_mines(),
+            time,
+            app.elapsed.as_secs(),
+            status
         ),
         Style::default()
             // TODO: why only one style?
@@ -71,129 +75,175 @@ where
     f.render_widget(paragraph, area);
 }
 
-fn draw_screen<B>(f: &mut Frame<B>, area: Rect, field: &game::Field)
+fn draw_screen<B>(f: &mut Frame<B>, area: Rect, app: &mut app::App)
 where
     B: Backend,
 {
+    let columns = app.field.config.columns() as u16;
+    let rows = app.field.config.rows() as u16;
+    // + 2 for borders
+    let fits = columns * CELL_WIDTH + 2 <= area.width && rows + 2 + CONFIG_HEIGHT <= area.height;
+    let map_height = if fits {
+        0
+    } else {
+        rows.min(MAP_MAX_HEIGHT) + 2
+    };
+
+    // only keep whole cells so clicks on the right edge aren't ambiguous
+    let field_width =
+        (columns * CELL_WIDTH).min(area.width.saturating_sub(2) / CELL_WIDTH * CELL_WIDTH) + 2;
+    let field_height = (rows + 2).min(area.height.saturating_sub(CONFIG_HEIGHT + map_height));
+
     // TODO: responsive layout vertical /horizontal
     let chunks = Layout::default()
         .direction(Direction::Horizontal)
+        .constraints([Constraint::Length(field_width), Constraint::Min(5)].as_ref())
+        .split(area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
         .constraints(
             [
-                // * 2 because double size
-                // + 2 for borders
-                Constraint::Length(field.config.columns as u16 * 2 + 2),
-                Constraint::Min(5),
+                Constraint::Length(field_height),
+                Constraint::Length(CONFIG_HEIGHT),
+                Constraint::Length(map_height),
+                Constraint::Min(0),
             ]
             .as_ref(),
         )
-        .split(area);
-
-    draw_minefield(f, chunks[0], field);
+        .split(chunks[0]);
 
-    // let block = Block::default().borders(Borders::ALL);
-
-    // let paragraph = Paragraph::new(field.as_text_ascii(true))
-    //     .block(block)
-    //     .wrap(Wrap { trim: true });
-    // let paragraph = Paragraph::new(field.as_lines(false).map(|text| Spans::from(text)).collect()).block(block).wrap(Wrap { trim: true });
+    // remember where the cells are drawn to map clicks to them and scroll the visible part
+    let field_area = Block::default().borders(Borders::ALL).inner(chunks[0]);
+    app.field_area = field_area;
+    app.cell_width = CELL_WIDTH;
+    app.update_viewport(
+        (field_area.width / CELL_WIDTH) as usize,
+        field_area.height as usize,
+    );
 
-    // f.render_widget(paragraph, chunks[1]);
+    draw_minefield(f, chunks[0], app);
+    draw_field_config(f, chunks[1], app);
+    if !fits {
+        draw_minimap(f, chunks[2], app);
+    }
 }
 
-fn draw_minefield<B>(f: &mut Frame<B>, area: Rect, field: &game::Field)
+fn draw_minefield<B>(f: &mut Frame<B>, area: Rect, app: &app::App)
 where
     B: Backend,
 {
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints(
-            [
-                // + 2 for borders
-                Constraint::Length(field.config.rows as u16 + 2),
-                Constraint::Max(4),
-                Constraint::Max(0),
-            ]
-            .as_ref(),
-        )
-        .split(area);
-
-    let number_styles = vec![
-        Style::default(),
-        // 1
-        Style::default().fg(Color::LightBlue),
-        // 2
-        Style::default().fg(Color::LightGreen),
-        // 3
-        Style::default().fg(Color::LightYellow),
-        // 4
-        Style::default().fg(Color::LightMagenta),
-        // 5
-        Style::default().fg(Color::LightRed),
-        // 6
-        Style::default()
-            .fg(Color::Yellow)
-            .add_modifier(Modifier::BOLD),
-        // 7
-        Style::default()
-            .fg(Color::Blue)
-            .add_modifier(Modifier::BOLD),
-        // 8
-        Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
-        // 9 Mine
-        Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
-        // 10 Empty
-        Style::default(),
-    ];
+    let field = &app.field;
 
-    // actual minefield
-    let block = Block::default().borders(Borders::ALL);
+    // actual minefield, only the part inside of the viewport
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(app.theme.border);
+    let scroll = app.scroll;
+    let columns = field.config.columns();
+    let rows = field.config.rows();
+    // show where the mines were and which flags were wrong
+    let reveal_all = field.state() == game::GameState::Lost;
     let paragraph = Paragraph::new(
-        field
-            .get_field()
-            .into_iter()
-            .map(|line| {
+        (scroll.y..(scroll.y + app.view_rows).min(rows))
+            .map(|y| {
                 Spans::from(
-                    line.into_iter()
-                        .map(|cell| {
-                            Span::styled(
-                                cell_to_string(cell),
-                                *number_styles.get(cell as usize).unwrap(),
-                            )
+                    (scroll.x..(scroll.x + app.view_columns).min(columns))
+                        .map(|x| {
+                            // the visible cells are all on the board
+                            let view = field
+                                .view(x + y * columns, reveal_all)
+                                .unwrap_or(game::CellView::Hidden);
+                            let glyph = cell_glyph(&app.theme, view);
+                            let mut style = glyph.style;
+                            let coord = game::Coord { x, y };
+                            match &app.playback {
+                                // cell of the last action of the replay
+                                Some(playback) if playback.target == Some(coord) => {
+                                    style = style.bg(Color::Yellow).fg(Color::Black);
+                                }
+                                None if app.cursor == coord => {
+                                    style = style.add_modifier(Modifier::REVERSED);
+                                }
+                                _ => {}
+                            }
+                            Span::styled(glyph.symbol.as_str(), style)
                         })
                         .collect::<Vec<_>>(),
                 )
             })
             .collect::<Vec<_>>(),
     )
-    .block(block);
+    .block(block)
+    .style(app.theme.board);
     // not necessary
     // .wrap(Wrap { trim: false });
 
-    f.render_widget(paragraph, chunks[0]);
+    f.render_widget(paragraph, area);
+}
 
-    draw_field_config(f, chunks[1], field);
+/**
+ * Scaled down view of the whole board where the visible part is highlighted
+ */
+fn draw_minimap<B>(f: &mut Frame<B>, area: Rect, app: &app::App)
+where
+    B: Backend,
+{
+    let block = Block::default().title("Map").borders(Borders::ALL);
+    let inner = block.inner(area);
+    let columns = app.field.config.columns();
+    let rows = app.field.config.rows();
+    let width = (inner.width as usize)
+        .min(MAP_MAX_WIDTH as usize)
+        .min(columns);
+    let height = (inner.height as usize).min(rows);
+
+    let visible_style = Style::default().fg(Color::LightCyan);
+    let lines = (0..height)
+        .map(|my| {
+            // rows of the board covered by this line of the map
+            let top = my * rows / height;
+            let bottom = (my + 1) * rows / height;
+            let visible_y = top < app.scroll.y + app.view_rows && bottom > app.scroll.y;
+            Spans::from(
+                (0..width)
+                    .map(|mx| {
+                        let left = mx * columns / width;
+                        let right = (mx + 1) * columns / width;
+                        let visible_x =
+                            left < app.scroll.x + app.view_columns && right > app.scroll.x;
+                        if visible_x && visible_y {
+                            Span::styled("#", visible_style)
+                        } else {
+                            Span::raw(".")
+                        }
+                    })
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    f.render_widget(Paragraph::new(lines).block(block), area);
 }
 
-fn cell_to_string(mines: u32) -> String {
-    if mines == 0 {
-        String::from("  ")
-    } else if mines == 8 {
-        String::from("🚩")
-    } else if mines == 9 {
-        String::from("💣")
-    } else if mines == 10 {
-        String::from("🔲")
-    } else {
-        let codepoint = 0x245f + mines as u16;
-        String::from_utf16(&[codepoint, 0x20]).unwrap()
+fn cell_glyph(theme: &theme::Theme, view: game::CellView) -> &theme::Glyph {
+    match view {
+        game::CellView::Hidden => &theme.hidden,
+        game::CellView::Flagged => &theme.flag,
+        game::CellView::Questioned => &theme.question,
+        game::CellView::Revealed(0) => &theme.empty,
+        game::CellView::Revealed(n) => &theme.numbers[n as usize - 1],
+        game::CellView::Mine => &theme.mine,
+        game::CellView::ExplodedMine => &theme.exploded,
+        game::CellView::WrongFlag => &theme.wrong_flag,
     }
 }
 
-fn draw_field_config<B>(f: &mut Frame<B>, area: Rect, field: &game::Field)
+fn draw_field_config<B>(f: &mut Frame<B>, area: Rect, app: &app::App)
 where
     B: Backend,
 {
+    let field = &app.field;
     let block = Block::default()
         .title("Config")
         // .style(Style::default().fg(Color::White).bg(Color::Black))
@@ -205,11 +255,29 @@ where
     let text = vec![
         Spans::from(vec![
             Span::styled("Size: ", label_style),
-            Span::raw(format!("{}x{}", field.config.columns, field.config.rows)),
+            Span::raw(format!(
+                "{}x{}",
+                field.config.columns(),
+                field.config.rows()
+            )),
+            Span::styled("  3BV: ", label_style),
+            Span::raw(match field.bbbv() {
+                Some(bbbv) => bbbv.to_string(),
+                // the mines are placed on the first reveal
+                None => String::from("?"),
+            }),
         ]),
         Spans::from(vec![
             Span::styled("Mines: ", label_style),
-            Span::from(format!("{} 💣", field.config.mines)),
+            Span::from(if app.enhanced_graphics {
+                format!("{} 💣", field.config.mines())
+            } else {
+                field.config.mines().to_string()
+            }),
+        ]),
+        Spans::from(vec![
+            Span::styled("Undos: ", label_style),
+            Span::raw(field.undo_count().to_string()),
         ]),
     ];
 
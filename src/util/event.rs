@@ -12,6 +12,7 @@ use termion::input::TermRead;
 
 pub enum Event<I> {
     Input(I),
+    #[allow(dead_code)]
     Drag(termion::event::MouseButton, u16, u16),
     Click(termion::event::MouseButton, u16, u16),
     Tick,
@@ -21,8 +22,11 @@ pub enum Event<I> {
 /// type is handled in its own thread and returned to a common `Receiver`
 pub struct Events {
     rx: mpsc::Receiver<Event<Key>>,
+    #[allow(dead_code)]
     input_handle: thread::JoinHandle<()>,
+    #[allow(dead_code)]
     ignore_exit_key: Arc<AtomicBool>,
+    #[allow(dead_code)]
     tick_handle: thread::JoinHandle<()>,
 }
 
@@ -47,6 +51,7 @@ enum MousePress {
 }
 
 impl Events {
+    #[allow(dead_code)]
     pub fn new() -> Events {
         Events::with_config(Config::default())
     }
@@ -61,45 +66,54 @@ impl Events {
 
             thread::spawn(move || {
                 let stdin = io::stdin();
-                for evt in stdin.events() {
-                    if let Ok(key_or_mouse) = evt {
-                        match key_or_mouse {
-                            termion::event::Event::Key(key) => {
-                                if let Err(err) = tx.send(Event::Input(key)) {
-                                    eprintln!("{}", err);
-                                    return;
+                for key_or_mouse in stdin.events().flatten() {
+                    match key_or_mouse {
+                        termion::event::Event::Key(key) => {
+                            if let Err(err) = tx.send(Event::Input(key)) {
+                                eprintln!("{}", err);
+                                return;
+                            }
+                            if !ignore_exit_key.load(Ordering::Relaxed) && key == config.exit_key {
+                                return;
+                            }
+                        }
+                        termion::event::Event::Mouse(mouse_event) => match mouse_event {
+                            termion::event::MouseEvent::Release(x, y) => {
+                                if let MousePress::Button(button, _, _) = current_mouse_press {
+                                    if let Err(err) = tx.send(Event::Click(button, x, y)) {
+                                        eprintln!("{}", err);
+                                        return;
+                                    }
                                 }
-                                if !ignore_exit_key.load(Ordering::Relaxed)
-                                    && key == config.exit_key
+                                current_mouse_press = MousePress::None;
+                            }
+                            termion::event::MouseEvent::Hold(x, y) => {
+                                if let MousePress::Button(_, current_x, current_y) =
+                                    &mut current_mouse_press
                                 {
-                                    return;
+                                    *current_x = x;
+                                    *current_y = y;
                                 }
                             }
-                            termion::event::Event::Mouse(mouse_event) => match mouse_event {
-                                termion::event::MouseEvent::Release(x, y) => {
-                                    if let MousePress::Button(button, _, _) = current_mouse_press {
-                                        if let Err(err) = tx.send(Event::Click(button, x, y)) {
+                            termion::event::MouseEvent::Press(mouse_button, x, y) => {
+                                match mouse_button {
+                                    // the wheel never sends a release
+                                    termion::event::MouseButton::WheelUp
+                                    | termion::event::MouseButton::WheelDown => {
+                                        if let Err(err) = tx.send(Event::Click(mouse_button, x, y))
+                                        {
                                             eprintln!("{}", err);
                                             return;
                                         }
                                     }
-                                    current_mouse_press = MousePress::None;
-                                }
-                                termion::event::MouseEvent::Hold(x, y) => {
-                                    if let MousePress::Button(_, current_x, current_y) =
-                                        &mut current_mouse_press
-                                    {
-                                        *current_x = x;
-                                        *current_y = y;
+                                    _ => {
+                                        current_mouse_press =
+                                            MousePress::Button(mouse_button, x, y);
                                     }
                                 }
-                                termion::event::MouseEvent::Press(mouse_button, x, y) => {
-                                    current_mouse_press = MousePress::Button(mouse_button, x, y);
-                                }
-                                _ => {}
-                            },
-                            _ => {}
-                        }
+                            }
+                        },
+                        _ => {}
                     }
                 }
             })
@@ -124,10 +138,12 @@ impl Events {
         self.rx.recv()
     }
 
+    #[allow(dead_code)]
     pub fn disable_exit_key(&mut self) {
         self.ignore_exit_key.store(true, Ordering::Relaxed);
     }
 
+    #[allow(dead_code)]
     pub fn enable_exit_key(&mut self) {
         self.ignore_exit_key.store(false, Ordering::Relaxed);
     }